@@ -0,0 +1,330 @@
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::Bytes,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use tokio::sync::{OwnedSemaphorePermit, mpsc};
+
+use crate::{
+    api::fila::{self, BackendFila},
+    appstate::AppState,
+    configuracao::ConfigAdmissao,
+    intervencao::Acao,
+    roteamento,
+};
+
+// Lugar garantido para um pagamento antes mesmo de ele passar pela deduplicação: o que
+// foi aceito nunca fica esperando vaga.
+pub enum Vaga<'a> {
+    Direta(OwnedSemaphorePermit),
+    Canal(usize, mpsc::Permit<'a, Bytes>),
+    // A fila durável não tem capacidade local; o XADD decide.
+    Duravel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recusa {
+    pub status: StatusCode,
+    pub retry_after_secs: u64,
+    pub motivo: &'static str,
+}
+
+impl IntoResponse for Recusa {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            [(header::RETRY_AFTER, self.retry_after_secs.to_string())],
+            self.motivo,
+        )
+            .into_response()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Carga {
+    pub na_fila: usize,
+    // Pagamentos por segundo que os workers tiram da fila; 0 sem processador apto.
+    pub vazao: f64,
+    pub pausa_restante: Option<Duration>,
+}
+
+impl Carga {
+    pub fn drenagem(&self) -> Duration {
+        if self.vazao <= 0.0 {
+            return Duration::MAX;
+        }
+        Duration::try_from_secs_f64(self.na_fila as f64 / self.vazao).unwrap_or(Duration::MAX)
+    }
+}
+
+fn retry_after(config: &ConfigAdmissao, espera: Duration) -> u64 {
+    (espera.as_secs_f64().ceil() as u64).clamp(1, config.retry_after_max_secs)
+}
+
+// 503 quando nada sai da fila (pausa ou nenhum processador apto), com Retry-After até a
+// situação poder mudar; 429 quando sai, mas não a tempo, com o tempo até a fila voltar
+// ao limite.
+pub fn avaliar(
+    config: &ConfigAdmissao,
+    carga: &Carga,
+    intervalo_saude: Duration,
+) -> Result<(), Recusa> {
+    if !config.habilitada || carga.na_fila < config.fila_minima {
+        return Ok(());
+    }
+    if let Some(restante) = carga.pausa_restante {
+        return Err(Recusa {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            retry_after_secs: retry_after(config, restante),
+            motivo: "despacho pausado",
+        });
+    }
+    if carga.vazao <= 0.0 {
+        return Err(Recusa {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            retry_after_secs: retry_after(config, intervalo_saude),
+            motivo: "nenhum processador disponível",
+        });
+    }
+    let drenagem = carga.drenagem();
+    if drenagem > config.drenagem_max {
+        return Err(Recusa {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            retry_after_secs: retry_after(config, drenagem - config.drenagem_max),
+            motivo: "fila acima do que os workers esvaziam a tempo",
+        });
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Apto {
+    pub prioridade: u8,
+    pub taxa: f64,
+    pub latencia_ms: u64,
+    // Limite de concorrência do processador.
+    pub vagas: usize,
+}
+
+// Pagamentos por segundo com `consumidores` tarefas (workers e tarefas diretas), cada uma
+// com um pagamento por vez. Os processadores aptos são ocupados na ordem de preferência
+// da estratégia de prioridade, até o limite de concorrência de cada um.
+pub fn estimar_vazao(mut aptos: Vec<Apto>, consumidores: usize) -> f64 {
+    aptos.sort_by(|a, b| roteamento::preferencia((a.prioridade, a.taxa), (b.prioridade, b.taxa)));
+    let mut restantes = consumidores;
+    let mut vazao = 0.0;
+    for apto in aptos {
+        let ocupadas = apto.vagas.min(restantes);
+        vazao += ocupadas as f64 * 1000.0 / apto.latencia_ms.max(1) as f64;
+        restantes -= ocupadas;
+    }
+    vazao
+}
+
+// Mesmas regras do roteamento: drenado fica de fora e, com um forçado, só ele conta.
+pub async fn medir_carga(state: &AppState) -> Carga {
+    let agora = Instant::now();
+    let agora_utc = Utc::now();
+    let mut aptos = Vec::with_capacity(state.processors.len());
+    let mut forcado = None;
+    for processor in state.processors.iter() {
+        let guard = processor.read().await;
+        let acao = guard.intervencao.ativa(agora_utc).map(|i| i.acao);
+        let apto = acao != Some(Acao::Drenar)
+            && (!guard.failing || matches!(acao, Some(Acao::Forcar | Acao::Saudavel)))
+            && guard.disjuntor.disponivel(agora);
+        if !apto {
+            continue;
+        }
+        let (latencia_ms, _) = guard.desempenho(agora, &state.estatisticas);
+        let apto = Apto {
+            prioridade: guard.prioridade,
+            taxa: guard.taxa,
+            latencia_ms,
+            vagas: guard.limite.capacidade(),
+        };
+        if acao == Some(Acao::Forcar) {
+            forcado = Some(apto);
+        }
+        aptos.push(apto);
+    }
+    if let Some(forcado) = forcado {
+        aptos = vec![forcado];
+    }
+
+    let diretas_em_uso =
+        state.fila.permissoes_diretas as usize - state.fast_furious.available_permits();
+    let (na_fila, consumidores) = match state.fila.backend {
+        BackendFila::Memoria => (
+            state
+                .sender_queue
+                .iter()
+                .map(|sender| sender.max_capacity() - sender.capacity())
+                .sum::<usize>()
+                + diretas_em_uso,
+            state.fila.workers + state.fila.permissoes_diretas as usize,
+        ),
+        // O stream é de todas as instâncias, mas só os workers desta entram na conta: a
+        // estimativa erra para o lado de recusar. Sem resposta do Redis, o XADD decide.
+        BackendFila::RedisStreams => (
+            fila::tamanho_fila(state).await.unwrap_or(0),
+            state.fila.workers,
+        ),
+    };
+
+    Carga {
+        na_fila,
+        vazao: estimar_vazao(aptos, consumidores),
+        pausa_restante: state
+            .pausa
+            .lock()
+            .unwrap()
+            .ativa(agora_utc)
+            .and_then(|pausa| (pausa.expira_em - agora_utc).to_std().ok()),
+    }
+}
+
+// Decide se o pagamento entra e, se entra, já separa o lugar dele. Nunca espera por vaga,
+// a não ser com a admissão desabilitada (comportamento anterior). A carga é a última
+// medida em segundo plano, com até `ADMISSAO_MEDICAO_MS` de atraso.
+pub async fn reservar(state: &AppState) -> Result<Vaga<'_>, Recusa> {
+    let config = &state.admissao;
+    let carga = *state.carga.lock().unwrap();
+    if config.habilitada {
+        avaliar(config, &carga, state.saude.intervalo)?;
+    }
+    if state.fila.backend == BackendFila::RedisStreams {
+        return Ok(Vaga::Duravel);
+    }
+
+    if let Ok(permit) = state.fast_furious.clone().try_acquire_owned() {
+        return Ok(Vaga::Direta(permit));
+    }
+
+    let fechado = Recusa {
+        status: StatusCode::SERVICE_UNAVAILABLE,
+        retry_after_secs: 1,
+        motivo: "canal de workers fechado",
+    };
+    let canais = state.sender_queue.len();
+    let inicio = state.round_robin_counter.fetch_add(1, Ordering::Relaxed);
+    if !config.habilitada {
+        let canal = inicio % canais;
+        return match state.sender_queue[canal].reserve().await {
+            Ok(permit) => Ok(Vaga::Canal(canal, permit)),
+            Err(_) => Err(fechado),
+        };
+    }
+    // Canal da vez cheio: tenta os seguintes antes de recusar.
+    for deslocamento in 0..canais {
+        let canal = (inicio + deslocamento) % canais;
+        match state.sender_queue[canal].try_reserve() {
+            Ok(permit) => return Ok(Vaga::Canal(canal, permit)),
+            Err(mpsc::error::TrySendError::Full(())) => continue,
+            Err(mpsc::error::TrySendError::Closed(())) => return Err(fechado),
+        }
+    }
+    Err(Recusa {
+        status: StatusCode::TOO_MANY_REQUESTS,
+        retry_after_secs: retry_after(config, carga.drenagem()),
+        motivo: "filas cheias",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ConfigAdmissao {
+        ConfigAdmissao {
+            habilitada: true,
+            drenagem_max: Duration::from_secs(10),
+            fila_minima: 100,
+            retry_after_max_secs: 30,
+        }
+    }
+
+    fn carga(na_fila: usize, vazao: f64) -> Carga {
+        Carga {
+            na_fila,
+            vazao,
+            pausa_restante: None,
+        }
+    }
+
+    #[test]
+    fn recusa_com_429_quando_a_fila_nao_drena_a_tempo() {
+        let saude = Duration::from_secs(5);
+        assert_eq!(avaliar(&config(), &carga(1000, 100.0), saude), Ok(()));
+
+        let recusa = avaliar(&config(), &carga(1500, 100.0), saude).unwrap_err();
+        assert_eq!(recusa.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(recusa.retry_after_secs, 5);
+
+        // Retry-After limitado ao máximo configurado.
+        let recusa = avaliar(&config(), &carga(100_000, 100.0), saude).unwrap_err();
+        assert_eq!(recusa.retry_after_secs, 30);
+    }
+
+    #[test]
+    fn recusa_com_503_quando_nada_sai_da_fila() {
+        let saude = Duration::from_millis(4500);
+        let recusa = avaliar(&config(), &carga(100, 0.0), saude).unwrap_err();
+        assert_eq!(recusa.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(recusa.retry_after_secs, 5);
+
+        let pausada = Carga {
+            pausa_restante: Some(Duration::from_secs(12)),
+            ..carga(100, 1000.0)
+        };
+        let recusa = avaliar(&config(), &pausada, saude).unwrap_err();
+        assert_eq!(recusa.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(recusa.retry_after_secs, 12);
+    }
+
+    fn apto(prioridade: u8, latencia_ms: u64, vagas: usize) -> Apto {
+        Apto {
+            prioridade,
+            taxa: 0.05,
+            latencia_ms,
+            vagas,
+        }
+    }
+
+    #[test]
+    fn vazao_respeita_o_limite_de_cada_processador() {
+        // Sem limite, todos os consumidores vão para o mais prioritário.
+        let aptos = vec![apto(0, 100, usize::MAX), apto(1, 10, usize::MAX)];
+        assert_eq!(estimar_vazao(aptos, 50), 500.0);
+        // Com limite, o que sobra vai para o seguinte.
+        let aptos = vec![apto(0, 100, 20), apto(1, 50, 100)];
+        assert_eq!(estimar_vazao(aptos, 50), 200.0 + 600.0);
+        assert_eq!(estimar_vazao(vec![apto(0, 100, 20)], 50), 200.0);
+        assert_eq!(estimar_vazao(Vec::new(), 50), 0.0);
+    }
+
+    #[test]
+    fn vazao_segue_prioridades_trocadas_numa_recarga() {
+        // Registro na ordem do boot, prioridades já trocadas: o segundo é o principal.
+        let aptos = vec![apto(1, 100, usize::MAX), apto(0, 10, usize::MAX)];
+        assert_eq!(estimar_vazao(aptos, 50), 5000.0);
+    }
+
+    #[test]
+    fn fila_curta_ou_admissao_desabilitada_sempre_aceitam() {
+        let saude = Duration::from_secs(5);
+        assert_eq!(avaliar(&config(), &carga(99, 0.0), saude), Ok(()));
+
+        let desabilitada = ConfigAdmissao {
+            habilitada: false,
+            ..config()
+        };
+        assert_eq!(avaliar(&desabilitada, &carga(100_000, 0.0), saude), Ok(()));
+    }
+}
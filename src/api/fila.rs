@@ -0,0 +1,277 @@
+use axum::body::Bytes;
+use deadpool_redis::{
+    Connection,
+    redis::{
+        AsyncCommands, RedisError, Script,
+        streams::{
+            StreamAutoClaimOptions, StreamAutoClaimReply, StreamId, StreamReadOptions,
+            StreamReadReply,
+        },
+    },
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{api::redis::obter_conexao, appstate::AppState, constantes};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendFila {
+    Memoria,
+    #[serde(alias = "redis")]
+    RedisStreams,
+}
+
+impl BackendFila {
+    // `FILA_DURAVEL`: qualquer valor além de "redis"/"redis_streams" mantém a fila em memória.
+    pub fn from_env(valor: &str) -> Self {
+        match valor.to_ascii_uppercase().as_str() {
+            "REDIS" | "REDIS_STREAMS" => BackendFila::RedisStreams,
+            _ => BackendFila::Memoria,
+        }
+    }
+}
+
+pub struct EntradaFila {
+    pub id: String,
+    pub body: Vec<u8>,
+}
+
+// Entrada do stream nas mãos de um consumidor.
+#[derive(Clone, Copy, Debug)]
+pub struct Posse<'a> {
+    pub consumidor: &'a str,
+    pub id: &'a str,
+}
+
+fn converter_entradas(ids: Vec<StreamId>) -> Vec<EntradaFila> {
+    ids.into_iter()
+        .filter_map(|entrada| {
+            let body: Vec<u8> = entrada.get(constantes::FILA_CAMPO)?;
+            Some(EntradaFila {
+                id: entrada.id,
+                body,
+            })
+        })
+        .collect()
+}
+
+pub async fn criar_grupo_consumidores(state: &AppState) -> Result<(), RedisError> {
+    let mut conn = obter_conexao(state).await?;
+
+    let resultado: Result<(), RedisError> = conn
+        .xgroup_create_mkstream(constantes::FILA_STREAM, constantes::FILA_GRUPO, "0")
+        .await;
+
+    match resultado {
+        // O grupo já existir não é erro: outra instância (ou um boot anterior) o criou.
+        Err(e) if e.code() == Some("BUSYGROUP") => Ok(()),
+        other => other,
+    }
+}
+
+pub async fn enfileirar_pagamento(state: &AppState, body: &Bytes) -> Result<(), RedisError> {
+    let mut conn = obter_conexao(state).await?;
+
+    let _: String = conn
+        .xadd(
+            constantes::FILA_STREAM,
+            "*",
+            &[(constantes::FILA_CAMPO, body.as_ref())],
+        )
+        .await?;
+    Ok(())
+}
+
+// `inicio` = "0" relê as entradas já entregues a este consumidor e ainda não confirmadas;
+// `inicio` = ">" busca apenas entradas novas, bloqueando até `bloqueio_ms`.
+pub async fn ler_fila(
+    state: &AppState,
+    consumidor: &str,
+    inicio: &str,
+    quantidade: usize,
+    bloqueio_ms: usize,
+) -> Result<Vec<EntradaFila>, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+
+    let mut opcoes = StreamReadOptions::default()
+        .group(constantes::FILA_GRUPO, consumidor)
+        .count(quantidade);
+    if inicio == ">" {
+        opcoes = opcoes.block(bloqueio_ms);
+    }
+
+    let resposta: Option<StreamReadReply> = conn
+        .xread_options(&[constantes::FILA_STREAM], &[inicio], &opcoes)
+        .await?;
+
+    Ok(resposta
+        .map(|r| {
+            r.keys
+                .into_iter()
+                .flat_map(|chave| converter_entradas(chave.ids))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+// Assume entradas pendentes há mais de `ocioso_ms` em qualquer consumidor do grupo,
+// cobrindo instâncias que caíram com trabalho em andamento. Devolve uma página da PEL a
+// partir de `cursor` e o cursor da próxima; "0-0" quando ela terminou. Cada página deve
+// ser processada antes de buscar a seguinte: assumida e parada, a entrada envelhece de
+// novo.
+pub async fn reivindicar_orfaos(
+    state: &AppState,
+    consumidor: &str,
+    ocioso_ms: u64,
+    cursor: &str,
+    quantidade: usize,
+) -> Result<(String, Vec<EntradaFila>), RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    reivindicar_pagina(&mut conn, consumidor, ocioso_ms, cursor, quantidade).await
+}
+
+async fn reivindicar_pagina(
+    conn: &mut Connection,
+    consumidor: &str,
+    ocioso_ms: u64,
+    cursor: &str,
+    quantidade: usize,
+) -> Result<(String, Vec<EntradaFila>), RedisError> {
+    let resposta: StreamAutoClaimReply = conn
+        .xautoclaim_options(
+            constantes::FILA_STREAM,
+            constantes::FILA_GRUPO,
+            consumidor,
+            ocioso_ms,
+            cursor,
+            StreamAutoClaimOptions::default().count(quantidade),
+        )
+        .await?;
+    Ok((
+        resposta.next_stream_id,
+        converter_entradas(resposta.claimed),
+    ))
+}
+
+// Confirma que a entrada ainda está pendente em nome deste consumidor e zera o tempo
+// ocioso dela, para ninguém a reivindicar pelos próximos `ocioso_ms`. `false` quando outro
+// consumidor já a assumiu: a partir daí o envio é dele.
+pub async fn assumir_entrada(state: &AppState, posse: Posse<'_>) -> Result<bool, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    assumir(&mut conn, posse).await
+}
+
+async fn assumir(conn: &mut Connection, posse: Posse<'_>) -> Result<bool, RedisError> {
+    let script = Script::new(
+        r#"
+        local pendente = redis.call('XPENDING', KEYS[1], ARGV[1], ARGV[3], ARGV[3], 1, ARGV[2])
+        if #pendente == 0 then
+            return 0
+        end
+        redis.call('XCLAIM', KEYS[1], ARGV[1], ARGV[2], 0, ARGV[3], 'JUSTID')
+        return 1
+        "#,
+    );
+    let assumida: i64 = script
+        .key(constantes::FILA_STREAM)
+        .arg(constantes::FILA_GRUPO)
+        .arg(posse.consumidor)
+        .arg(posse.id)
+        .invoke_async(conn)
+        .await?;
+    Ok(assumida == 1)
+}
+
+// Entradas confirmadas são apagadas, então o tamanho do stream é o que falta processar
+// (inclusive o que está em andamento em qualquer instância).
+pub async fn tamanho_fila(state: &AppState) -> Result<usize, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    conn.xlen(constantes::FILA_STREAM).await
+}
+
+pub async fn confirmar_entrada(state: &AppState, id: &str) -> Result<(), RedisError> {
+    let mut conn = obter_conexao(state).await?;
+
+    let () = deadpool_redis::redis::pipe()
+        .atomic()
+        .xack(constantes::FILA_STREAM, constantes::FILA_GRUPO, &[id])
+        .ignore()
+        .xdel(constantes::FILA_STREAM, &[id])
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use deadpool_redis::{Config, Runtime};
+
+    use super::*;
+    use crate::constantes::{FILA_CAMPO, FILA_GRUPO, FILA_STREAM};
+
+    // O stream da fila em `REDIS_URL_TESTE` é apagado.
+    #[tokio::test]
+    #[ignore = "precisa de um Redis em REDIS_URL_TESTE"]
+    async fn entrada_lenta_nao_e_enviada_por_dois_consumidores() {
+        let url = std::env::var("REDIS_URL_TESTE")
+            .unwrap_or_else(|_| "redis://127.0.0.1:6379/15".to_string());
+        let pool = Config::from_url(url)
+            .create_pool(Some(Runtime::Tokio1))
+            .unwrap();
+        let mut conn = pool.get().await.unwrap();
+        let () = conn.del(FILA_STREAM).await.unwrap();
+        let () = conn
+            .xgroup_create_mkstream(FILA_STREAM, FILA_GRUPO, "0")
+            .await
+            .unwrap();
+        let id: String = conn
+            .xadd(FILA_STREAM, "*", &[(FILA_CAMPO, "{}")])
+            .await
+            .unwrap();
+        let ocioso_ms = 50;
+
+        // `a` lê a entrada e demora mais que `ocioso_ms` para chegar ao envio; nesse meio
+        // tempo a varredura de `b` a reivindica.
+        let opcoes = StreamReadOptions::default().group(FILA_GRUPO, "a").count(1);
+        let lida: StreamReadReply = conn
+            .xread_options(&[FILA_STREAM], &[">"], &opcoes)
+            .await
+            .unwrap();
+        assert_eq!(lida.keys[0].ids[0].id, id);
+        tokio::time::sleep(Duration::from_millis(ocioso_ms * 2)).await;
+        let (cursor, reivindicadas) = reivindicar_pagina(&mut conn, "b", ocioso_ms, "0-0", 1)
+            .await
+            .unwrap();
+        assert_eq!(cursor, "0-0");
+        assert_eq!(reivindicadas.len(), 1);
+
+        // Os dois conferem a posse antes de enviar; só um envia.
+        let mut envios = 0;
+        for consumidor in ["b", "a"] {
+            let posse = Posse {
+                consumidor,
+                id: &id,
+            };
+            if assumir(&mut conn, posse).await.unwrap() {
+                envios += 1;
+            }
+        }
+        assert_eq!(envios, 1);
+
+        // Quem renova a posse não perde a entrada, por mais que demore.
+        tokio::time::sleep(Duration::from_millis(ocioso_ms * 2)).await;
+        let posse = Posse {
+            consumidor: "b",
+            id: &id,
+        };
+        assert!(assumir(&mut conn, posse).await.unwrap());
+        let (_, reivindicadas) = reivindicar_pagina(&mut conn, "a", ocioso_ms, "0-0", 1)
+            .await
+            .unwrap();
+        assert!(reivindicadas.is_empty());
+    }
+}
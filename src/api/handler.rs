@@ -1,97 +1,775 @@
 use axum::{
     body::Bytes,
-    extract::{Json, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Json, Path, Query, Request, State},
+    http::{HeaderMap, HeaderName, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
 };
-use rust_decimal::Decimal;
-use std::{str::FromStr, sync::atomic::Ordering};
+use chrono::Utc;
+use futures::{StreamExt, stream};
+use std::time::{Duration, Instant};
+use tracing::{Instrument, debug, error, info_span, warn};
+use uuid::Uuid;
 
 use crate::{
-    api::redis,
+    admissao::{self, Recusa, Vaga},
+    api::{
+        fila::{self, BackendFila},
+        http::{self, ConsultaPagamento},
+        redis,
+    },
     appstate::AppState,
-    models::{self, data_range::DateRangeParams, payment::Payment, summary::PaymentSummary},
+    armazenamento, constantes,
+    intervencao::{self, PedidoIntervencao},
+    models::{
+        data_range::DateRangeParams,
+        dead_letter::PaginacaoParams,
+        dinheiro::Centavos,
+        expurgo::{ExpurgoParams, RegistroExpurgo},
+        lote::{self, ItemLote, RespostaLote, ResultadoItem},
+        payment::Payment,
+        prontidao::{
+            Prontidao, SaudeConhecida, VerificacaoFilas, VerificacaoNats, VerificacaoRedis,
+            VerificacaoSaude,
+        },
+        status::{EstadoPagamento, StatusPagamento},
+        summary::{PaymentSummary, RelatorioConsistencia, Summary},
+    },
+    recarga,
 };
 
-pub async fn submit_work_handler(State(state): State<AppState>, body: Bytes) -> StatusCode {
-    let semaphore = state.fast_furious.clone();
+pub async fn submit_work_handler(State(state): State<AppState>, body: Bytes) -> Response {
+    let mut bytes_vec = body.to_vec();
+    let payload: Payment = match simd_json::from_slice(&mut bytes_vec) {
+        Ok(p) => p,
+        Err(e) => {
+            state
+                .metricas
+                .recebidos
+                .with_label_values(&["invalido"])
+                .inc();
+            debug!(erro = %e, "corpo de pagamento inválido");
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+
+    match admitir_pagamento(&state, payload, body).await {
+        Admissao::Aceito => StatusCode::OK.into_response(),
+        Admissao::Duplicado => resposta_duplicada(),
+        Admissao::ErroRedis => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        Admissao::Recusado(recusa) => recusa.into_response(),
+    }
+}
+
+// Aceita um array JSON ou NDJSON (`Content-Type: application/x-ndjson`, ou qualquer corpo
+// que não comece com `[`). Cada item passa pela mesma admissão de `POST /payments` e
+// recebe seu próprio resultado; a resposta é 200 mesmo com itens recusados.
+pub async fn submit_batch_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let ndjson = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|tipo| {
+            tipo.starts_with("application/x-ndjson") || tipo.starts_with("application/jsonl")
+        })
+        || body
+            .iter()
+            .find(|b| !b.is_ascii_whitespace())
+            .is_none_or(|b| *b != b'[');
+    let itens = match lote::ler_lote(&body, ndjson) {
+        Ok(itens) => itens,
+        Err(erro) => return (StatusCode::BAD_REQUEST, erro).into_response(),
+    };
+    if itens.len() > state.lote.max_itens {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("lote com mais de {} itens", state.lote.max_itens),
+        )
+            .into_response();
+    }
+
+    // `buffered` mantém a ordem dos itens na resposta.
+    let resultados: Vec<ItemLote> = stream::iter(itens.into_iter().enumerate())
+        .map(|(indice, item)| admitir_item_lote(&state, indice, item))
+        .buffered(state.lote.concorrencia)
+        .collect()
+        .await;
+    let retry_after = resultados.iter().filter_map(|i| i.retry_after_secs).max();
+    let mut resposta = (StatusCode::OK, Json(RespostaLote::new(resultados))).into_response();
+    if let Some(segundos) = retry_after {
+        resposta
+            .headers_mut()
+            .insert(header::RETRY_AFTER, segundos.into());
+    }
+    resposta
+}
+
+async fn admitir_item_lote(
+    state: &AppState,
+    indice: usize,
+    item: Result<Payment, String>,
+) -> ItemLote {
+    let payload = match item {
+        Ok(payload) => payload,
+        Err(erro) => {
+            state
+                .metricas
+                .recebidos
+                .with_label_values(&["invalido"])
+                .inc();
+            return ItemLote {
+                indice,
+                correlation_id: None,
+                resultado: ResultadoItem::Invalido,
+                erro: Some(erro),
+                retry_after_secs: None,
+            };
+        }
+    };
+
+    // Só id e valor seguem adiante, como no corpo de `POST /payments` lido pelo worker.
+    let payment = Payment {
+        correlation_id: payload.correlation_id,
+        amount: payload.amount,
+        requested_at: None,
+        tipo: None,
+    };
+    let body = Bytes::from(serde_json::to_vec(&payment).unwrap());
+    let (resultado, erro, retry_after_secs) = match admitir_pagamento(state, payment, body).await {
+        Admissao::Aceito => (ResultadoItem::Aceito, None, None),
+        Admissao::Duplicado => (ResultadoItem::Duplicado, None, None),
+        Admissao::ErroRedis => (
+            ResultadoItem::Rejeitado,
+            Some("Redis indisponível".to_string()),
+            None,
+        ),
+        Admissao::Recusado(recusa) => (
+            ResultadoItem::Rejeitado,
+            Some(recusa.motivo.to_string()),
+            Some(recusa.retry_after_secs),
+        ),
+    };
+    ItemLote {
+        indice,
+        correlation_id: Some(payload.correlation_id),
+        resultado,
+        erro,
+        retry_after_secs,
+    }
+}
 
-    if let Ok(permit) = semaphore.try_acquire_owned() {
-        let mut bytes_vec = body.to_vec();
-        let payload: Payment = match simd_json::from_slice(&mut bytes_vec) {
-            Ok(p) => p,
-            Err(_) => {
-                return StatusCode::BAD_REQUEST;
+enum Admissao {
+    Aceito,
+    Duplicado,
+    ErroRedis,
+    Recusado(Recusa),
+}
+
+// Admissão, dedup no Redis e despacho para os workers, com o span do pagamento a partir
+// daqui. A vaga é separada antes do dedup; um duplicado só a devolve.
+async fn admitir_pagamento(state: &AppState, payload: Payment, body: Bytes) -> Admissao {
+    let span = info_span!("pagamento", correlation_id = %payload.correlation_id);
+    async move {
+        let recebidos = &state.metricas.recebidos;
+        let vaga = match admissao::reservar(state).await {
+            Ok(vaga) => vaga,
+            Err(recusa) => {
+                recebidos.with_label_values(&["recusado"]).inc();
+                debug!(
+                    status = recusa.status.as_u16(),
+                    motivo = recusa.motivo,
+                    "pagamento recusado pela admissão"
+                );
+                return Admissao::Recusado(recusa);
             }
         };
+        match redis::reivindicar_correlation_id(state, &payload.correlation_id).await {
+            Ok(true) => {}
+            Ok(false) => {
+                recebidos.with_label_values(&["duplicado"]).inc();
+                debug!("correlationId repetido");
+                return Admissao::Duplicado;
+            }
+            Err(e) => {
+                recebidos.with_label_values(&["erro_redis"]).inc();
+                warn!(erro = %e, "falha ao reivindicar o correlationId");
+                return Admissao::ErroRedis;
+            }
+        }
 
-        let payment = Payment {
-            correlation_id: payload.correlation_id,
-            amount: payload.amount,
-            requested_at: None,
-            tipo: None,
-        };
+        let correlation_id = payload.correlation_id;
+        if let Err(recusa) = despachar_pagamento(state, vaga, payload, body).await {
+            recebidos.with_label_values(&["rejeitado"]).inc();
+            warn!(motivo = recusa.motivo, "pagamento não enfileirado");
+            // Não foi aceito: libera o id para que o cliente possa tentar de novo.
+            let _ = redis::liberar_correlation_id(state, &correlation_id).await;
+            return Admissao::Recusado(recusa);
+        }
+        recebidos.with_label_values(&["aceito"]).inc();
+        Admissao::Aceito
+    }
+    .instrument(span)
+    .await
+}
 
-        tokio::spawn(async move {
-            let _permit = permit;
-            crate::workers::consumer::processa_pagamento(state.clone(), payment).await;
-        });
+// Duplicatas recebem sempre a mesma resposta de sucesso: o pagamento original já foi aceito,
+// e um retry do nginx não deve virar erro para o cliente.
+fn resposta_duplicada() -> Response {
+    (
+        StatusCode::OK,
+        [(HeaderName::from_static("idempotent-replayed"), "true")],
+    )
+        .into_response()
+}
 
-        StatusCode::OK
-    } else {
-        let counter = state.round_robin_counter.fetch_add(1, Ordering::Relaxed);
-        let sender = &state.sender_queue[counter % state.sender_queue.len()];
+pub async fn despachar_pagamento(
+    state: &AppState,
+    vaga: Vaga<'_>,
+    payload: Payment,
+    body: Bytes,
+) -> Result<(), Recusa> {
+    match vaga {
+        Vaga::Duravel => match fila::enfileirar_pagamento(state, &body).await {
+            Ok(_) => {
+                debug!(destino = "redis_streams", "pagamento enfileirado");
+                Ok(())
+            }
+            Err(e) => {
+                warn!(erro = %e, "falha ao publicar no stream");
+                Err(Recusa {
+                    status: StatusCode::SERVICE_UNAVAILABLE,
+                    retry_after_secs: 1,
+                    motivo: "fila durável indisponível",
+                })
+            }
+        },
+        Vaga::Direta(permit) => {
+            let payment = Payment {
+                correlation_id: payload.correlation_id,
+                amount: payload.amount,
+                requested_at: None,
+                tipo: None,
+            };
 
-        match sender.send(body).await {
-            Ok(_) => StatusCode::OK,
+            debug!(destino = "direto", "pagamento enfileirado");
+            let state = state.clone();
+            // O span atual segue com a task: tentativas e gravação ficam dentro do mesmo pagamento.
+            tokio::spawn(
+                async move {
+                    let _permit = permit;
+                    crate::workers::consumer::processa_pagamento(state, payment, None).await;
+                }
+                .in_current_span(),
+            );
+            Ok(())
+        }
+        Vaga::Canal(canal, permit) => {
+            permit.send(body);
+            debug!(destino = "canal", canal, "pagamento enfileirado");
+            Ok(())
+        }
+    }
+}
 
-            Err(_) => StatusCode::SERVICE_UNAVAILABLE,
+pub async fn buscar_status_pagamento(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match redis::buscar_status(&state, &id).await {
+        Ok(Some(status)) => return (StatusCode::OK, Json(status)).into_response(),
+        Ok(None) => {}
+        Err(e) => {
+            warn!(correlation_id = %id, erro = %e, "falha ao buscar status");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    }
+
+    // O status expira depois de `STATUS_TTL_SECS`; o pagamento gravado continua valendo.
+    match state.armazenamento.buscar(&id).await {
+        Ok(Some(pagamento)) => {
+            let status = StatusPagamento {
+                correlation_id: id,
+                estado: EstadoPagamento::Confirmado,
+                tipo: Some(pagamento.tipo),
+                requested_at: Some(pagamento.requested_at),
+                tentativas: 0,
+                ultimo_erro: None,
+            };
+            (StatusCode::OK, Json(status)).into_response()
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            warn!(correlation_id = %id, erro = %e, "falha ao buscar pagamento gravado");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
         }
     }
 }
 
-pub async fn purge_payments(State(state): State<AppState>) -> StatusCode {
-    match redis::expurgar_todos_pagamentos(&state).await {
-        Ok(_) => StatusCode::OK,
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+// Sem filtro, apaga tudo o que é deste serviço; com `from`/`to`/`processador`, só os
+// pagamentos que casam, descontados dos agregados.
+pub async fn purge_payments(
+    State(state): State<AppState>,
+    Query(params): Query<ExpurgoParams>,
+) -> Response {
+    let filtro = params.filtro();
+    let removidos = match state.armazenamento.expurgar(&filtro).await {
+        Ok(r) => r,
+        Err(e) => {
+            error!(erro = %e, "falha no expurgo do armazenamento");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    // Status, dedup e dead letters vivem no Redis seja qual for o armazenamento.
+    let chaves = if filtro.completo() {
+        redis::expurgar_estado_completo(&state).await
+    } else {
+        redis::expurgar_estado_pagamentos(&state, &removidos.ids).await
+    };
+
+    let registro = RegistroExpurgo {
+        registrado_em: Utc::now(),
+        instancia: state.instancia.to_string(),
+        from: params.from,
+        to: params.to,
+        processador: params.processador,
+        pagamentos: removidos
+            .totais
+            .into_iter()
+            .map(|(nome, total_requests, total_centavos)| {
+                let summary = Summary {
+                    total_requests,
+                    total_amount: Centavos(total_centavos).to_decimal(),
+                };
+                (nome, summary)
+            })
+            .collect(),
+        chaves_removidas: chaves.as_ref().copied().unwrap_or(0),
+    };
+    let auditado = redis::registrar_auditoria_expurgo(&state, &registro).await;
+
+    if let Err(e) = &chaves {
+        error!(erro = %e, "falha ao expurgar o estado no Redis");
+    }
+    if let Err(e) = &auditado {
+        error!(erro = %e, "falha ao registrar a auditoria do expurgo");
+    }
+    if chaves.is_err() || auditado.is_err() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(registro)).into_response();
+    }
+    (StatusCode::OK, Json(registro)).into_response()
+}
+
+async fn montar_sumario(
+    state: &AppState,
+    totais: Vec<armazenamento::TotalProcessador>,
+) -> PaymentSummary {
+    let mut summary = PaymentSummary::default();
+    for processor in state.processors.iter() {
+        summary
+            .processadores
+            .insert(processor.read().await.nome.clone(), Summary::default());
+    }
+    for (nome, total_requests, total_centavos) in totais {
+        summary.processadores.insert(
+            nome,
+            Summary {
+                total_requests,
+                total_amount: Centavos(total_centavos).to_decimal(),
+            },
+        );
     }
+    summary
+}
+
+// O que passou da retenção pode já ter sido compactado; fica fora do sumário sempre,
+// para a resposta não depender de a compactação ter rodado ou não.
+fn intervalo_retido(state: &AppState, params: &DateRangeParams) -> (u64, u64) {
+    let (from_ts, to_ts) = params.intervalo_micros();
+    let horizonte = state
+        .retencao
+        .horizonte_micros(Utc::now().timestamp_micros() as u64);
+    (from_ts.max(horizonte), to_ts)
 }
 
 pub async fn get_payment_summary(
     State(state): State<AppState>,
     Query(params): Query<DateRangeParams>,
 ) -> impl IntoResponse {
-    let from_ts = params
-        .from
-        .map(|dt| dt.timestamp_micros() as u64)
-        .unwrap_or(0);
-    let to_ts = params
-        .to
-        .map(|dt| dt.timestamp_micros() as u64)
-        .unwrap_or(u64::MAX);
-
-    match redis::coletar_entre_timestamp(&state, from_ts, to_ts).await {
-        Ok((default_reqs, default_amt_str, fallback_reqs, fallback_amt_str)) => {
-            let summary = PaymentSummary {
-                default: models::summary::Summary {
-                    total_requests: default_reqs,
-                    total_amount: Decimal::from_str(&default_amt_str).unwrap_or(Decimal::ZERO),
-                },
-                fallback: models::summary::Summary {
-                    total_requests: fallback_reqs,
-                    total_amount: Decimal::from_str(&fallback_amt_str).unwrap_or(Decimal::ZERO),
-                },
-            };
+    let (from_ts, to_ts) = intervalo_retido(&state, &params);
+
+    match state.armazenamento.sumario(from_ts, to_ts).await {
+        Ok(totais) => {
+            let summary = montar_sumario(&state, totais).await;
             (StatusCode::OK, Json(summary)).into_response()
         }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Falha ao buscar o sumário de pagamentos.".to_string(),
+        Err(e) => {
+            error!(erro = %e, "falha ao buscar o sumário");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Falha ao buscar o sumário de pagamentos.".to_string(),
+            )
+                .into_response()
+        }
+    }
+}
+
+// Compara o sumário dos baldes com a soma pagamento a pagamento no mesmo intervalo.
+pub async fn verificar_consistencia(
+    State(state): State<AppState>,
+    Query(params): Query<DateRangeParams>,
+) -> impl IntoResponse {
+    let (from_ts, to_ts) = intervalo_retido(&state, &params);
+
+    let (agregado, varredura) = match tokio::try_join!(
+        state.armazenamento.sumario(from_ts, to_ts),
+        state.armazenamento.sumario_varredura(from_ts, to_ts),
+    ) {
+        Ok(totais) => totais,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let agregado = montar_sumario(&state, agregado).await;
+    let varredura = montar_sumario(&state, varredura).await;
+    let relatorio = RelatorioConsistencia {
+        consistente: agregado == varredura,
+        agregado,
+        varredura,
+    };
+    (StatusCode::OK, Json(relatorio)).into_response()
+}
+
+pub async fn listar_dead_letters(
+    State(state): State<AppState>,
+    Query(params): Query<PaginacaoParams>,
+) -> impl IntoResponse {
+    let offset = params.offset.unwrap_or(0);
+    let limit = params.limit.unwrap_or(100).min(1000);
+
+    match redis::listar_dead_letters(&state, offset, limit).await {
+        Ok(dead_letters) => (StatusCode::OK, Json(dead_letters)).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+pub async fn buscar_dead_letter(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match redis::buscar_dead_letter(&state, &id.to_string()).await {
+        Ok(Some(dead_letter)) => (StatusCode::OK, Json(dead_letter)).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+pub async fn reprocessar_dead_letter(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    let id = id.to_string();
+    let mut dead_letter = match redis::buscar_dead_letter(&state, &id).await {
+        Ok(Some(d)) => d,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    // Antes de reenviar algo que pode já ter sido cobrado, pergunta ao processador.
+    if dead_letter.resultado_desconhecido {
+        let address = match &dead_letter.processador {
+            Some(nome) => buscar_endereco_processador(&state, nome).await,
+            None => None,
+        };
+        let Some(address) = address else {
+            return (
+                StatusCode::CONFLICT,
+                "Processador da tentativa original não está mais registrado.".to_string(),
+            )
+                .into_response();
+        };
+
+        match http::consultar_pagamento_processador(
+            &state.http_client,
+            &address,
+            &dead_letter.payment.correlation_id,
+        )
+        .await
+        {
+            ConsultaPagamento::Cobrado => {
+                dead_letter.cobrado = true;
+                dead_letter
+                    .payment
+                    .set_processador(dead_letter.processador.clone().unwrap_or_default());
+            }
+            ConsultaPagamento::NaoEncontrado => {}
+            ConsultaPagamento::Indefinido(erro) => {
+                return (StatusCode::CONFLICT, erro).into_response();
+            }
+        }
+    }
+
+    match redis::remover_dead_letter(&state, &id).await {
+        Ok(true) => {}
+        // Outro replay concorrente já assumiu esta entrada.
+        Ok(false) => return StatusCode::NOT_FOUND.into_response(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+
+    let span = info_span!("replay", correlation_id = %dead_letter.payment.correlation_id);
+    tokio::spawn(
+        async move {
+            if dead_letter.cobrado {
+                if let Err(e) = armazenamento::salvar_pagamento(&state, &dead_letter.payment).await
+                {
+                    error!(erro = %e, "replay cobrado não gravado, devolvendo à dead letter");
+                    let _ = redis::salvar_dead_letter(&dead_letter, &state).await;
+                }
+            } else {
+                crate::workers::consumer::processa_pagamento(state, dead_letter.payment, None)
+                    .await;
+            }
+        }
+        .instrument(span),
+    );
+
+    StatusCode::ACCEPTED.into_response()
+}
+
+async fn buscar_endereco_processador(state: &AppState, nome: &str) -> Option<String> {
+    for processor in state.processors.iter() {
+        let guard = processor.read().await;
+        if guard.nome == nome {
+            return Some(guard.address.clone());
+        }
+    }
+    None
+}
+
+pub async fn descartar_dead_letter(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> StatusCode {
+    match redis::remover_dead_letter(&state, &id.to_string()).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+pub async fn vivo() -> StatusCode {
+    StatusCode::OK
+}
+
+// 503 quando esta instância não deve receber tráfego; o corpo diz o porquê.
+pub async fn prontidao(State(state): State<AppState>) -> Response {
+    let limite = Duration::from_millis(constantes::PRONTIDAO_TIMEOUT_MS);
+
+    let status_pool = state.redis_pool.status();
+    let inicio = Instant::now();
+    let erro = match tokio::time::timeout(limite, redis::ping(&state)).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some("sem resposta dentro do limite".to_string()),
+    };
+    let redis = VerificacaoRedis {
+        ok: erro.is_none(),
+        latencia_ms: erro.is_none().then(|| inicio.elapsed().as_millis() as u64),
+        conexoes_livres: status_pool.available,
+        conexoes_max: status_pool.max_size,
+        aguardando: status_pool.waiting,
+        erro,
+    };
+
+    let estado_nats = state.nats_client.connection_state();
+    let nats = VerificacaoNats {
+        ok: estado_nats == async_nats::connection::State::Connected,
+        estado: estado_nats.to_string(),
+    };
+
+    let agora = Utc::now();
+    let mut processadores = Vec::with_capacity(state.processors.len());
+    for processor in state.processors.iter() {
+        let guard = processor.read().await;
+        processadores.push(SaudeConhecida {
+            nome: guard.nome.clone(),
+            saude_em: guard.saude_em,
+            obsoleta: guard.saude_obsoleta(agora, state.iniciado_em, state.saude.validade),
+        });
+    }
+
+    let filas = match state.fila.backend {
+        BackendFila::Memoria => {
+            let canais: Vec<_> = state
+                .sender_queue
+                .iter()
+                .map(|sender| {
+                    (
+                        sender.max_capacity() - sender.capacity(),
+                        sender.max_capacity(),
+                    )
+                })
+                .collect();
+            VerificacaoFilas::new(&canais, constantes::PRONTIDAO_OCUPACAO_MAXIMA)
+        }
+        BackendFila::RedisStreams => VerificacaoFilas::duravel(),
+    };
+
+    let prontidao = Prontidao::new(
+        state.encerramento.encerrando(),
+        redis,
+        nats,
+        VerificacaoSaude::new(processadores),
+        filas,
+    );
+    let status = if prontidao.pronto {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(prontidao)).into_response()
+}
+
+pub async fn listar_processadores(State(state): State<AppState>) -> impl IntoResponse {
+    let mut resumos = Vec::with_capacity(state.processors.len());
+    for processor in state.processors.iter() {
+        resumos.push(processor.read().await.resumo(&state.estatisticas));
+    }
+    (StatusCode::OK, Json(resumos))
+}
+
+pub async fn definir_intervencao(
+    State(state): State<AppState>,
+    Path(nome): Path<String>,
+    Json(pedido): Json<PedidoIntervencao>,
+) -> Response {
+    let intervencao = match pedido.validar(false, Utc::now()) {
+        Ok(intervencao) => intervencao,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    match intervencao::definir(&state, Some(nome), Some(intervencao)).await {
+        Ok(mensagem) => (StatusCode::OK, Json(mensagem)).into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e).into_response(),
+    }
+}
+
+pub async fn remover_intervencao(
+    State(state): State<AppState>,
+    Path(nome): Path<String>,
+) -> Response {
+    match intervencao::definir(&state, Some(nome), None).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e).into_response(),
+    }
+}
+
+pub async fn exibir_pausa(State(state): State<AppState>) -> impl IntoResponse {
+    let pausa = state.pausa.lock().unwrap().ativa(Utc::now()).cloned();
+    (StatusCode::OK, Json(pausa))
+}
+
+pub async fn pausar(
+    State(state): State<AppState>,
+    Json(pedido): Json<PedidoIntervencao>,
+) -> Response {
+    let intervencao = match pedido.validar(true, Utc::now()) {
+        Ok(intervencao) => intervencao,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    match intervencao::definir(&state, None, Some(intervencao)).await {
+        Ok(mensagem) => (StatusCode::OK, Json(mensagem)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn retomar(State(state): State<AppState>) -> Response {
+    match intervencao::definir(&state, None, None).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn exibir_configuracao(State(state): State<AppState>) -> impl IntoResponse {
+    (StatusCode::OK, Json(state.recarga.config_atual().await))
+}
+
+// Fragmento no formato do arquivo (TOML, ou JSON com `Content-Type: application/json`)
+// sobreposto à configuração atual. Aplica o que dá para mudar sem reiniciar e repassa
+// às outras instâncias via NATS.
+pub async fn recarregar_configuracao(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|tipo| tipo.starts_with("application/json"));
+    let fragmento: Result<toml::Table, String> = if json {
+        serde_json::from_slice::<serde_json::Value>(&body)
+            .map_err(|e| e.to_string())
+            .and_then(|valor| toml::Table::try_from(valor).map_err(|e| e.to_string()))
+    } else {
+        std::str::from_utf8(&body)
+            .map_err(|e| e.to_string())
+            .and_then(|texto| toml::from_str(texto).map_err(|e| e.to_string()))
+    };
+    let atual = state.recarga.config_atual().await;
+    let nova = match fragmento.and_then(|fragmento| atual.mesclar(fragmento)) {
+        Ok(nova) => nova,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    match recarga::recarregar(&state, nova).await {
+        Ok(resultado) => (StatusCode::OK, Json(resultado)).into_response(),
+        Err(e) => (StatusCode::UNPROCESSABLE_ENTITY, e).into_response(),
+    }
+}
+
+// Rotas administrativas exigem `X-Admin-Token` igual a `ADMIN_TOKEN`. Sem a variável,
+// ficam fechadas.
+pub async fn exigir_token_admin(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(esperado) = state.admin_token.as_deref() else {
+        return (
+            StatusCode::FORBIDDEN,
+            "ADMIN_TOKEN não configurado nesta instância.".to_string(),
         )
-            .into_response(),
+            .into_response();
+    };
+    let recebido = request
+        .headers()
+        .get("x-admin-token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    if !tokens_iguais(recebido.as_bytes(), esperado.as_bytes()) {
+        return StatusCode::UNAUTHORIZED.into_response();
     }
+    next.run(request).await
+}
+
+// Compara sem sair no primeiro byte diferente.
+fn tokens_iguais(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub async fn exportar_metricas(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        state.metricas.renderizar(&state).await,
+    )
 }
 
-pub async fn handle_tower_error(err: tower::BoxError) -> StatusCode {
+pub async fn handle_tower_error(_err: tower::BoxError) -> StatusCode {
     StatusCode::SERVICE_UNAVAILABLE
 }
@@ -1,11 +1,43 @@
-use std::time::Duration;
+use reqwest::StatusCode;
+use uuid::Uuid;
 
-pub fn cria_cliente_http() -> reqwest::Client {
+use crate::configuracao::ConfigPagamentos;
+
+pub fn cria_cliente_http(config: &ConfigPagamentos) -> reqwest::Client {
     reqwest::Client::builder()
-        .timeout(Duration::from_secs(5))
-        .connect_timeout(Duration::from_secs(2))
+        .timeout(config.timeout)
+        .connect_timeout(config.timeout_conexao)
         .tcp_nodelay(true)
         .pool_max_idle_per_host(500)
         .build()
         .unwrap()
 }
+
+pub enum ConsultaPagamento {
+    Cobrado,
+    NaoEncontrado,
+    Indefinido(String),
+}
+
+// `GET {address}/payments/{correlationId}` do processador: é a única forma de saber se
+// um POST que expirou chegou a ser cobrado.
+pub async fn consultar_pagamento_processador(
+    client: &reqwest::Client,
+    address: &str,
+    correlation_id: &Uuid,
+) -> ConsultaPagamento {
+    match client
+        .get(format!("{}/payments/{}", address, correlation_id))
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => ConsultaPagamento::Cobrado,
+        Ok(response) if response.status() == StatusCode::NOT_FOUND => {
+            ConsultaPagamento::NaoEncontrado
+        }
+        Ok(response) => {
+            ConsultaPagamento::Indefinido(format!("consulta respondeu {}", response.status()))
+        }
+        Err(e) => ConsultaPagamento::Indefinido(format!("falha na consulta: {}", e)),
+    }
+}
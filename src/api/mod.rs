@@ -1,3 +1,4 @@
+pub mod fila;
 pub mod handler;
 pub mod http;
 pub mod nats;
@@ -1,13 +1,11 @@
-use crate::constantes;
 use std::time::Duration;
 
-pub async fn cria_cliente_nats() -> async_nats::Client {
-    let nats_url = std::env::var("NATS_URL").unwrap_or_else(|_| constantes::NATS_URL.to_string());
+pub async fn cria_cliente_nats(nats_url: &str) -> async_nats::Client {
     let max_tentativas = 5u8;
     let delay = 5;
 
     for tentativa in 1..=max_tentativas {
-        match async_nats::connect(&nats_url).await {
+        match async_nats::connect(nats_url).await {
             Ok(cliente) => {
                 return cliente;
             }
@@ -1,28 +1,35 @@
 use deadpool::managed::Pool;
 use deadpool_redis::{
     Config, Connection, Manager, PoolConfig, Runtime,
-    redis::{RedisError, Script},
+    redis::{ErrorKind, RedisError, Script},
 };
 use futures::future;
 
-use std::{env, time::Duration};
+use chrono::{DateTime, Utc};
+use std::{collections::HashMap, time::Duration};
+use uuid::Uuid;
 
-use crate::{appstate::AppState, constantes, models};
-
-pub async fn estabelecer_pool_conexao()
--> deadpool::managed::Pool<Manager, deadpool_redis::Connection> {
-    let db_url = env::var("DB_URL").unwrap_or_else(|_| constantes::REDIS_URL.to_string());
-    let pool_qtd: usize = (env::var("NUM_CONSUMER")
-        .unwrap_or_else(|_| constantes::NUM_CONSUMER.to_string()))
-    .parse()
-    .unwrap();
+use crate::{
+    appstate::AppState,
+    configuracao::ConfigRedis,
+    constantes,
+    intervencao::MensagemIntervencao,
+    models::{
+        self,
+        status::{EstadoPagamento, StatusPagamento},
+    },
+};
 
+pub async fn estabelecer_pool_conexao(
+    config: &ConfigRedis,
+    workers: usize,
+) -> deadpool::managed::Pool<Manager, deadpool_redis::Connection> {
     let max_tentativas = 5u8;
     let delay_secs = 5u64;
 
     for tentativa in 1..=max_tentativas {
-        let mut cfg = Config::from_url(&db_url);
-        cfg.pool = Some(PoolConfig::new(pool_qtd * 3));
+        let mut cfg = Config::from_url(&config.url);
+        cfg.pool = Some(PoolConfig::new(workers * config.conexoes_por_worker));
 
         match cfg.create_pool(Some(Runtime::Tokio1)) {
             Ok(pool) => {
@@ -41,118 +48,390 @@ pub async fn estabelecer_pool_conexao()
         max_tentativas
     );
 }
-pub async fn salvar_pagamento(pagamento: &models::payment::Payment, state: &AppState) {
-    let max_tentativas = 50u8;
-    let retry_delay = tokio::time::Duration::from_millis(1);
-    let pagamento_chave = format!("payment:{}", &pagamento.correlation_id);
-    let pagamento_tempo = pagamento.requested_at.unwrap().timestamp_micros() as u64;
-    let pagamento_json = match simd_json::to_string(&pagamento) {
-        Ok(json) => json,
-        Err(_) => {
-            return;
-        }
+pub async fn obter_conexao(state: &AppState) -> Result<Connection, RedisError> {
+    state.redis_pool.get().await.map_err(|e| {
+        RedisError::from((ErrorKind::IoError, "pool Redis indisponível", e.to_string()))
+    })
+}
+
+pub async fn ping(state: &AppState) -> Result<(), RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let _: String = redis::cmd("PING").query_async(&mut conn).await?;
+    Ok(())
+}
+
+fn chave_status(id: &Uuid) -> String {
+    format!("payment:{}:status", id)
+}
+
+// SET NX no Redis compartilhado garante que só uma das instâncias aceita cada correlationId
+// dentro da janela, mesmo quando o nginx reenvia a requisição para a outra API.
+// Na mesma ida ao Redis o status nasce como Recebido.
+pub async fn reivindicar_correlation_id(state: &AppState, id: &Uuid) -> Result<bool, RedisError> {
+    let _cronometro = state
+        .metricas
+        .redis
+        .with_label_values(&["dedup"])
+        .start_timer();
+    let mut conn = obter_conexao(state).await?;
+    let script = Script::new(
+        r#"
+        if not redis.call('SET', KEYS[1], 1, 'NX', 'EX', ARGV[1]) then
+            return 0
+        end
+        redis.call('DEL', KEYS[2])
+        redis.call('HSET', KEYS[2], 'estado', ARGV[3], 'tentativas', 0)
+        redis.call('EXPIRE', KEYS[2], ARGV[2])
+        return 1
+    "#,
+    );
+
+    let reivindicado: u8 = script
+        .key(format!("dedup:{}", id))
+        .key(chave_status(id))
+        .arg(state.pagamentos().dedup_janela_secs)
+        .arg(state.pagamentos().status_ttl_secs)
+        .arg(EstadoPagamento::Recebido.as_str())
+        .invoke_async(&mut conn)
+        .await?;
+
+    Ok(reivindicado == 1)
+}
+
+pub async fn liberar_correlation_id(state: &AppState, id: &Uuid) -> Result<(), RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let () = redis::cmd("DEL")
+        .arg(format!("dedup:{}", id))
+        .arg(chave_status(id))
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn registrar_tentativa(
+    state: &AppState,
+    pagamento: &models::payment::Payment,
+    tipo: &str,
+) -> Result<(), RedisError> {
+    let _cronometro = state
+        .metricas
+        .redis
+        .with_label_values(&["status_tentativa"])
+        .start_timer();
+    let chave = chave_status(&pagamento.correlation_id);
+    let requested_at = pagamento
+        .requested_at
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default();
+    let mut conn = obter_conexao(state).await?;
+
+    let () = redis::pipe()
+        .atomic()
+        .hset_multiple(
+            &chave,
+            &[
+                ("estado", EstadoPagamento::Despachado.as_str()),
+                ("tipo", tipo),
+                ("requestedAt", requested_at.as_str()),
+            ],
+        )
+        .ignore()
+        .hincr(&chave, "tentativas", 1)
+        .ignore()
+        .expire(&chave, state.pagamentos().status_ttl_secs as i64)
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn registrar_confirmado(
+    state: &AppState,
+    pagamento: &models::payment::Payment,
+) -> Result<(), RedisError> {
+    let _cronometro = state
+        .metricas
+        .redis
+        .with_label_values(&["status_confirmado"])
+        .start_timer();
+    let chave = chave_status(&pagamento.correlation_id);
+    let requested_at = pagamento
+        .requested_at
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default();
+    let mut conn = obter_conexao(state).await?;
+
+    let () = redis::pipe()
+        .atomic()
+        .hset_multiple(
+            &chave,
+            &[
+                ("estado", EstadoPagamento::Confirmado.as_str()),
+                ("tipo", pagamento.tipo.as_deref().unwrap_or_default()),
+                ("requestedAt", requested_at.as_str()),
+            ],
+        )
+        .ignore()
+        .expire(&chave, state.pagamentos().status_ttl_secs as i64)
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn registrar_falha(state: &AppState, id: &Uuid, erro: &str) -> Result<(), RedisError> {
+    registrar_erro(state, id, EstadoPagamento::Falhou, erro).await
+}
+
+pub async fn registrar_desconhecido(
+    state: &AppState,
+    id: &Uuid,
+    erro: &str,
+) -> Result<(), RedisError> {
+    registrar_erro(state, id, EstadoPagamento::Desconhecido, erro).await
+}
+
+async fn registrar_erro(
+    state: &AppState,
+    id: &Uuid,
+    estado: EstadoPagamento,
+    erro: &str,
+) -> Result<(), RedisError> {
+    let _cronometro = state
+        .metricas
+        .redis
+        .with_label_values(&["status_erro"])
+        .start_timer();
+    let chave = chave_status(id);
+    let mut conn = obter_conexao(state).await?;
+
+    let () = redis::pipe()
+        .atomic()
+        .hset_multiple(&chave, &[("estado", estado.as_str()), ("ultimoErro", erro)])
+        .ignore()
+        .expire(&chave, state.pagamentos().status_ttl_secs as i64)
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn buscar_status(
+    state: &AppState,
+    id: &Uuid,
+) -> Result<Option<StatusPagamento>, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let campos: HashMap<String, String> = redis::cmd("HGETALL")
+        .arg(chave_status(id))
+        .query_async(&mut conn)
+        .await?;
+
+    let Some(estado) = campos
+        .get("estado")
+        .and_then(|e| EstadoPagamento::from_nome(e))
+    else {
+        return Ok(None);
     };
-    for _ in 1..=max_tentativas {
-        let mut conn = match state.redis_pool.get().await {
-            Ok(c) => c,
-            Err(_) => {
-                tokio::time::sleep(retry_delay).await;
-                continue;
-            }
-        };
 
-        let sorted_set_key = "payments_by_date";
+    Ok(Some(StatusPagamento {
+        correlation_id: *id,
+        estado,
+        tipo: campos.get("tipo").cloned(),
+        requested_at: campos
+            .get("requestedAt")
+            .and_then(|dt| DateTime::parse_from_rfc3339(dt).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
+        tentativas: campos
+            .get("tentativas")
+            .and_then(|t| t.parse().ok())
+            .unwrap_or(0),
+        ultimo_erro: campos.get("ultimoErro").cloned(),
+    }))
+}
 
-        let result: Result<(), redis::RedisError> = redis::pipe()
-            .atomic()
-            .set(&pagamento_chave, &pagamento_json)
-            .expire(&pagamento_chave, 80)
-            .zadd(sorted_set_key, &pagamento_chave, &pagamento_tempo)
+// Dedup e status dos pagamentos expurgados, para que os mesmos ids possam ser reenviados.
+pub async fn expurgar_estado_pagamentos(state: &AppState, ids: &[Uuid]) -> Result<u64, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let mut removidas = 0;
+    for lote in ids.chunks(500) {
+        let mut chaves = Vec::with_capacity(lote.len() * 2);
+        for id in lote {
+            chaves.push(format!("dedup:{}", id));
+            chaves.push(chave_status(id));
+        }
+        let apagadas: u64 = redis::cmd("UNLINK")
+            .arg(&chaves)
             .query_async(&mut conn)
-            .await;
+            .await?;
+        removidas += apagadas;
+    }
+    Ok(removidas)
+}
 
-        match result {
-            Ok(_) => {
-                return;
+// Expurgo completo do estado que este serviço guarda no Redis além dos pagamentos:
+// dedup, status, dead letters e as sobras do encerramento. Varre só os próprios
+// prefixos; o resto do banco (e o stream da fila, com trabalho ainda em andamento) fica
+// intacto.
+pub async fn expurgar_estado_completo(state: &AppState) -> Result<u64, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let mut removidas = 0;
+
+    for padrao in ["dedup:*", "payment:*:status"] {
+        let mut cursor = 0u64;
+        loop {
+            let (proximo, chaves): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(padrao)
+                .arg("COUNT")
+                .arg(1000)
+                .query_async(&mut conn)
+                .await?;
+            if !chaves.is_empty() {
+                let apagadas: u64 = redis::cmd("UNLINK")
+                    .arg(&chaves)
+                    .query_async(&mut conn)
+                    .await?;
+                removidas += apagadas;
             }
-            Err(_) => {
-                tokio::time::sleep(retry_delay).await;
+            if proximo == 0 {
+                break;
             }
+            cursor = proximo;
         }
     }
+
+    let apagadas: u64 = redis::cmd("UNLINK")
+        .arg(constantes::DEAD_LETTERS)
+        .arg(constantes::DEAD_LETTERS_POR_DATA)
+        .arg(constantes::SOBRAS_ENCERRAMENTO)
+        .query_async(&mut conn)
+        .await?;
+    Ok(removidas + apagadas)
 }
 
-pub async fn coletar_entre_timestamp(
+pub async fn registrar_auditoria_expurgo(
     state: &AppState,
-    from: u64,
-    to: u64,
-) -> Result<(u64, String, u64, String), RedisError> {
-    let mut conn = state.redis_pool.get().await.unwrap();
+    registro: &models::expurgo::RegistroExpurgo,
+) -> Result<(), RedisError> {
+    let json = serde_json::to_string(registro).map_err(|e| {
+        RedisError::from((ErrorKind::ParseError, "registro inválido", e.to_string()))
+    })?;
+    let mut conn = obter_conexao(state).await?;
 
-    let sorted_set_key = "payments_by_date";
+    let () = redis::pipe()
+        .atomic()
+        .lpush(constantes::AUDITORIA_EXPURGOS, json)
+        .ignore()
+        .ltrim(
+            constantes::AUDITORIA_EXPURGOS,
+            0,
+            constantes::AUDITORIA_EXPURGOS_MAX - 1,
+        )
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
 
-    let script = Script::new(
-        r#"
-        local keys = redis.call('ZRANGEBYSCORE', KEYS[1], ARGV[1], ARGV[2])
-        if #keys == 0 then
-            return {0, 0, 0, 0}
-        end
+pub async fn salvar_dead_letter(
+    dead_letter: &models::dead_letter::DeadLetter,
+    state: &AppState,
+) -> Result<(), RedisError> {
+    let _cronometro = state
+        .metricas
+        .redis
+        .with_label_values(&["dead_letter"])
+        .start_timer();
+    let id = dead_letter.payment.correlation_id.to_string();
+    let json = serde_json::to_string(dead_letter).map_err(|e| {
+        RedisError::from((ErrorKind::ParseError, "dead letter inválida", e.to_string()))
+    })?;
+    let mut conn = obter_conexao(state).await?;
 
-        local default_reqs = 0
-        local default_amt = 0.0
-        local fallback_reqs = 0
-        local fallback_amt = 0.0
-        
-        -- Processa as chaves em lotes de 3000 para evitar limites do Lua
-        local chunk_size = 3000
-        for i = 1, #keys, chunk_size do
-            -- Cria uma subtabela (chunk) para a chamada MGET
-            local chunk_keys = {}
-            for j = i, math.min(i + chunk_size - 1, #keys) do
-                table.insert(chunk_keys, keys[j])
-            end
+    let status_chave = chave_status(&dead_letter.payment.correlation_id);
+    let () = redis::pipe()
+        .atomic()
+        .hset(constantes::DEAD_LETTERS, &id, json)
+        .ignore()
+        .hset_multiple(
+            &status_chave,
+            &[
+                ("estado", EstadoPagamento::DeadLetter.as_str()),
+                ("ultimoErro", dead_letter.ultimo_erro.as_str()),
+            ],
+        )
+        .ignore()
+        .expire(&status_chave, state.pagamentos().status_ttl_secs as i64)
+        .ignore()
+        .zadd(
+            constantes::DEAD_LETTERS_POR_DATA,
+            &id,
+            dead_letter.registrado_em.timestamp_micros(),
+        )
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
 
-            -- Busca os valores para o lote atual de chaves
-            local values = redis.call('MGET', unpack(chunk_keys))
-
-            for _, json_str in ipairs(values) do
-                if json_str then
-                    -- cjson é o parser de JSON embutido no Redis
-                    local data = cjson.decode(json_str)
-                    if data.tipo == 'Default' then
-                        default_reqs = default_reqs + 1
-                        default_amt = default_amt + data.amount
-                    elseif data.tipo == 'Fallback' then
-                        fallback_reqs = fallback_reqs + 1
-                        fallback_amt = fallback_amt + data.amount
-                    end
-                end
-            end
-        end
+pub async fn listar_dead_letters(
+    state: &AppState,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<models::dead_letter::DeadLetter>, RedisError> {
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+    let mut conn = obter_conexao(state).await?;
 
-        return {
-            default_reqs,
-            string.format('%.4f', default_amt),
-            fallback_reqs,
-            string.format('%.4f', fallback_amt)
-        }
-    "#,
-    );
+    let ids: Vec<String> = redis::cmd("ZRANGE")
+        .arg(constantes::DEAD_LETTERS_POR_DATA)
+        .arg(offset)
+        .arg(offset + limit - 1)
+        .query_async(&mut conn)
+        .await?;
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    let summary_data: (u64, String, u64, String) = script
-        .key(sorted_set_key)
-        .arg(from)
-        .arg(to)
-        .invoke_async(&mut conn)
+    let valores: Vec<Option<String>> = redis::cmd("HMGET")
+        .arg(constantes::DEAD_LETTERS)
+        .arg(&ids)
+        .query_async(&mut conn)
         .await?;
 
-    Ok(summary_data)
+    Ok(valores
+        .into_iter()
+        .flatten()
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect())
 }
 
-pub async fn expurgar_todos_pagamentos(state: &AppState) -> Result<(), RedisError> {
-    let mut conn = state.redis_pool.get().await.unwrap();
-    let () = redis::cmd("FLUSHDB").query_async(&mut conn).await?;
-    Ok(())
+pub async fn buscar_dead_letter(
+    state: &AppState,
+    id: &str,
+) -> Result<Option<models::dead_letter::DeadLetter>, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let valor: Option<String> = redis::cmd("HGET")
+        .arg(constantes::DEAD_LETTERS)
+        .arg(id)
+        .query_async(&mut conn)
+        .await?;
+
+    Ok(valor.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+pub async fn remover_dead_letter(state: &AppState, id: &str) -> Result<bool, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let (removidos, _): (u64, u64) = redis::pipe()
+        .atomic()
+        .hdel(constantes::DEAD_LETTERS, id)
+        .zrem(constantes::DEAD_LETTERS_POR_DATA, id)
+        .query_async(&mut conn)
+        .await?;
+
+    Ok(removidos > 0)
 }
 
 pub async fn pre_aquecer_pool_redis(pool: &Pool<Manager, Connection>, num_conexoes: usize) {
@@ -166,3 +445,182 @@ pub async fn pre_aquecer_pool_redis(pool: &Pool<Manager, Connection>, num_conexo
 
     future::join_all(tasks).await;
 }
+
+// Disputa (ou renova) a concessão de liderança. A concessão guarda "identidade|token";
+// quem já é dono só estende o prazo, e uma concessão nova sempre recebe o próximo token
+// do contador, que nunca volta atrás. Retorna o token, ou 0 se outra instância é líder.
+pub async fn disputar_lideranca(state: &AppState) -> Result<u64, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let script = Script::new(
+        r#"
+        local atual = redis.call('GET', KEYS[1])
+        if atual then
+            local dono, token = string.match(atual, '^(.*)|(%d+)$')
+            if dono == ARGV[1] then
+                redis.call('PEXPIRE', KEYS[1], ARGV[2])
+                return tonumber(token)
+            end
+            return 0
+        end
+        local token = redis.call('INCR', KEYS[2])
+        redis.call('SET', KEYS[1], ARGV[1] .. '|' .. token, 'PX', ARGV[2])
+        return token
+        "#,
+    );
+    script
+        .key(constantes::LIDER_CONCESSAO)
+        .key(constantes::LIDER_FENCING)
+        .arg(&state.lideranca.identidade)
+        .arg(state.lideranca.config.concessao.as_millis() as u64)
+        .invoke_async(&mut conn)
+        .await
+}
+
+// Guarda a última saúde publicada de um processador. Uma líder antiga (token menor) não
+// sobrescreve a snapshot de uma mais nova.
+pub async fn salvar_snapshot_saude(
+    state: &AppState,
+    nome: &str,
+    snapshot: &models::processor::SnapshotSaude,
+) -> Result<(), RedisError> {
+    let json = serde_json::to_string(snapshot).map_err(|e| {
+        RedisError::from((ErrorKind::ParseError, "snapshot inválida", e.to_string()))
+    })?;
+    let mut conn = obter_conexao(state).await?;
+    let script = Script::new(
+        r#"
+        local atual = redis.call('GET', KEYS[1])
+        if atual and cjson.decode(atual).token > tonumber(ARGV[2]) then
+            return 0
+        end
+        redis.call('SET', KEYS[1], ARGV[1])
+        return 1
+        "#,
+    );
+    let _: i64 = script
+        .key(format!("{}{}", constantes::SAUDE_SNAPSHOT, nome))
+        .arg(json)
+        .arg(snapshot.token)
+        .invoke_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn buscar_snapshot_saude(
+    state: &AppState,
+    nome: &str,
+) -> Result<Option<models::processor::SnapshotSaude>, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let json: Option<String> = redis::cmd("GET")
+        .arg(format!("{}{}", constantes::SAUDE_SNAPSHOT, nome))
+        .query_async(&mut conn)
+        .await?;
+    json.map(|j| {
+        serde_json::from_str(&j).map_err(|e| {
+            RedisError::from((ErrorKind::ParseError, "snapshot inválida", e.to_string()))
+        })
+    })
+    .transpose()
+}
+
+fn chave_intervencao(processador: Option<&str>) -> String {
+    match processador {
+        Some(nome) => format!("{}processador:{}", constantes::INTERVENCAO, nome),
+        None => format!("{}pausa", constantes::INTERVENCAO),
+    }
+}
+
+// Guarda a intervenção até ela expirar; remover apaga a chave.
+pub async fn salvar_intervencao(
+    state: &AppState,
+    mensagem: &MensagemIntervencao,
+) -> Result<(), RedisError> {
+    let chave = chave_intervencao(mensagem.processador.as_deref());
+    let mut conn = obter_conexao(state).await?;
+    let Some(intervencao) = &mensagem.intervencao else {
+        let _: i64 = redis::cmd("DEL").arg(chave).query_async(&mut conn).await?;
+        return Ok(());
+    };
+    let restante_ms = (intervencao.expira_em - mensagem.emitida_em)
+        .num_milliseconds()
+        .max(1);
+    let json = serde_json::to_string(mensagem).map_err(|e| {
+        RedisError::from((ErrorKind::ParseError, "intervenção inválida", e.to_string()))
+    })?;
+    let _: () = redis::cmd("SET")
+        .arg(chave)
+        .arg(json)
+        .arg("PX")
+        .arg(restante_ms)
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn buscar_intervencao(
+    state: &AppState,
+    processador: Option<&str>,
+) -> Result<Option<MensagemIntervencao>, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let json: Option<String> = redis::cmd("GET")
+        .arg(chave_intervencao(processador))
+        .query_async(&mut conn)
+        .await?;
+    json.map(|j| {
+        serde_json::from_str(&j).map_err(|e| {
+            RedisError::from((ErrorKind::ParseError, "intervenção inválida", e.to_string()))
+        })
+    })
+    .transpose()
+}
+
+// Devolve a concessão antes do prazo, só se ela ainda for desta instância com este token.
+pub async fn liberar_lideranca(state: &AppState, token: u64) -> Result<(), RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let script = Script::new(
+        r#"
+        if redis.call('GET', KEYS[1]) == ARGV[1] then
+            redis.call('DEL', KEYS[1])
+        end
+        return 0
+        "#,
+    );
+    let _: i64 = script
+        .key(constantes::LIDER_CONCESSAO)
+        .arg(format!("{}|{}", state.lideranca.identidade, token))
+        .invoke_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+// Pagamentos que uma instância não terminou antes de sair, para outra retomar.
+pub async fn guardar_sobra(
+    state: &AppState,
+    payment: &models::payment::Payment,
+) -> Result<(), RedisError> {
+    let json = serde_json::to_string(payment).map_err(|e| {
+        RedisError::from((ErrorKind::ParseError, "pagamento inválido", e.to_string()))
+    })?;
+    let mut conn = obter_conexao(state).await?;
+    let () = redis::cmd("RPUSH")
+        .arg(constantes::SOBRAS_ENCERRAMENTO)
+        .arg(json)
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn retirar_sobra(
+    state: &AppState,
+) -> Result<Option<models::payment::Payment>, RedisError> {
+    let mut conn = obter_conexao(state).await?;
+    let json: Option<String> = redis::cmd("LPOP")
+        .arg(constantes::SOBRAS_ENCERRAMENTO)
+        .query_async(&mut conn)
+        .await?;
+    json.map(|j| {
+        serde_json::from_str(&j)
+            .map_err(|e| RedisError::from((ErrorKind::ParseError, "sobra inválida", e.to_string())))
+    })
+    .transpose()
+}
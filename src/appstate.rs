@@ -1,12 +1,24 @@
-use std::sync::{Arc, atomic::AtomicUsize};
+use std::sync::{Arc, Mutex, atomic::AtomicUsize};
 
 use axum::body::Bytes;
+use chrono::{DateTime, Utc};
 use deadpool::managed::Pool;
 use deadpool_redis::{Connection, Manager};
 use reqwest::Client;
 use tokio::sync::{RwLock, mpsc};
 
-use crate::models::processor::Processor;
+use crate::{
+    admissao::Carga,
+    armazenamento::{PaymentStore, PoliticaRetencao},
+    configuracao::{ConfigAdmissao, ConfigFila, ConfigLote, ConfigPagamentos},
+    encerramento::Encerramento,
+    estatisticas::ConfigEstatisticas,
+    intervencao::Controle,
+    lideranca::Lideranca,
+    metricas::Metricas,
+    models::processor::{ConfigSaude, Processor},
+    recarga::Recarga,
+};
 
 #[derive(Clone)]
 pub struct AppState {
@@ -17,5 +29,30 @@ pub struct AppState {
     pub sender_queue: Arc<Vec<mpsc::Sender<Bytes>>>,
     pub round_robin_counter: Arc<AtomicUsize>,
     pub fast_furious: Arc<tokio::sync::Semaphore>,
-    pub retry_default_percentage: f32,
+    pub fila: ConfigFila,
+    pub armazenamento: Arc<dyn PaymentStore>,
+    pub retencao: PoliticaRetencao,
+    // HOSTNAME, usado para identificar a instância em registros compartilhados.
+    pub instancia: Arc<str>,
+    pub admin_token: Option<Arc<str>>,
+    pub metricas: Arc<Metricas>,
+    pub lideranca: Arc<Lideranca>,
+    pub saude: ConfigSaude,
+    pub estatisticas: ConfigEstatisticas,
+    pub encerramento: Arc<Encerramento>,
+    pub lote: ConfigLote,
+    pub admissao: ConfigAdmissao,
+    pub recarga: Arc<Recarga>,
+    pub iniciado_em: DateTime<Utc>,
+    // Pausa global de despacho, definida por um operador.
+    pub pausa: Arc<Mutex<Controle>>,
+    // Última medida de carga, renovada em segundo plano para a admissão.
+    pub carga: Arc<Mutex<Carga>>,
+}
+
+impl AppState {
+    // Valores atuais; podem mudar entre duas chamadas por causa de uma recarga.
+    pub fn pagamentos(&self) -> ConfigPagamentos {
+        self.recarga.valores().pagamentos
+    }
 }
@@ -0,0 +1,244 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+};
+
+use futures::future::{self, BoxFuture};
+use uuid::Uuid;
+
+use crate::{
+    armazenamento::{
+        ErroArmazenamento, FiltroExpurgo, PaymentStore, ResultadoExpurgo, TotalProcessador,
+        acumular,
+    },
+    models::payment::PagamentoArmazenado,
+};
+
+// Tudo no processo: serve para testes e para rodar uma única instância sem Redis
+// guardando pagamentos. Nada sobrevive a um restart.
+#[derive(Default)]
+pub struct ArmazenamentoMemoria {
+    dados: Mutex<DadosMemoria>,
+}
+
+#[derive(Default)]
+struct DadosMemoria {
+    pagamentos: HashMap<Uuid, PagamentoArmazenado>,
+    // (requestedAt em microssegundos, id): o id desempata pagamentos no mesmo instante.
+    por_data: BTreeSet<(u64, Uuid)>,
+}
+
+impl PaymentStore for ArmazenamentoMemoria {
+    fn salvar<'a>(
+        &'a self,
+        pagamento: &'a PagamentoArmazenado,
+    ) -> BoxFuture<'a, Result<bool, ErroArmazenamento>> {
+        let mut dados = self.dados.lock().unwrap();
+        if dados.pagamentos.contains_key(&pagamento.correlation_id) {
+            return Box::pin(future::ready(Ok(false)));
+        }
+        let tempo = pagamento.requested_at.timestamp_micros() as u64;
+        dados.por_data.insert((tempo, pagamento.correlation_id));
+        dados
+            .pagamentos
+            .insert(pagamento.correlation_id, pagamento.clone());
+        Box::pin(future::ready(Ok(true)))
+    }
+
+    fn sumario(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> BoxFuture<'_, Result<Vec<TotalProcessador>, ErroArmazenamento>> {
+        let dados = self.dados.lock().unwrap();
+        let mut totais: Vec<TotalProcessador> = Vec::new();
+        if from <= to {
+            for (_, id) in dados
+                .por_data
+                .range((from, Uuid::nil())..=(to, Uuid::max()))
+            {
+                let pagamento = &dados.pagamentos[id];
+                acumular(&mut totais, &pagamento.tipo, pagamento.centavos);
+            }
+        }
+        Box::pin(future::ready(Ok(totais)))
+    }
+
+    fn buscar<'a>(
+        &'a self,
+        id: &'a Uuid,
+    ) -> BoxFuture<'a, Result<Option<PagamentoArmazenado>, ErroArmazenamento>> {
+        let pagamento = self.dados.lock().unwrap().pagamentos.get(id).cloned();
+        Box::pin(future::ready(Ok(pagamento)))
+    }
+
+    fn expurgar<'a>(
+        &'a self,
+        filtro: &'a FiltroExpurgo,
+    ) -> BoxFuture<'a, Result<ResultadoExpurgo, ErroArmazenamento>> {
+        let mut dados = self.dados.lock().unwrap();
+        let DadosMemoria {
+            pagamentos,
+            por_data,
+        } = &mut *dados;
+
+        let mut resultado = ResultadoExpurgo::default();
+        por_data.retain(|(tempo, id)| {
+            let pagamento = &pagamentos[id];
+            if !filtro.aceita(*tempo, &pagamento.tipo) {
+                return true;
+            }
+            acumular(&mut resultado.totais, &pagamento.tipo, pagamento.centavos);
+            resultado.ids.push(*id);
+            false
+        });
+        for id in &resultado.ids {
+            pagamentos.remove(id);
+        }
+        Box::pin(future::ready(Ok(resultado)))
+    }
+
+    fn compactar(&self, antes_de: u64) -> BoxFuture<'_, Result<u64, ErroArmazenamento>> {
+        let mut dados = self.dados.lock().unwrap();
+        // `split_off` devolve a parte a partir do horizonte e deixa o resto para trás.
+        let recentes = dados.por_data.split_off(&(antes_de, Uuid::nil()));
+        let expirados = std::mem::replace(&mut dados.por_data, recentes);
+        for (_, id) in &expirados {
+            dados.pagamentos.remove(id);
+        }
+        Box::pin(future::ready(Ok(expirados.len() as u64)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    fn pagamento(micros: i64, tipo: &str, centavos: i64) -> PagamentoArmazenado {
+        PagamentoArmazenado {
+            correlation_id: Uuid::from_u128(micros as u128),
+            centavos,
+            requested_at: DateTime::from_timestamp_micros(micros).unwrap(),
+            tipo: tipo.to_string(),
+        }
+    }
+
+    fn tudo() -> FiltroExpurgo {
+        FiltroExpurgo {
+            from: 0,
+            to: u64::MAX,
+            processador: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn regravacao_nao_conta_duas_vezes() {
+        let armazenamento = ArmazenamentoMemoria::default();
+        let p = pagamento(1_000_000, "default", 1990);
+        assert!(armazenamento.salvar(&p).await.unwrap());
+        assert!(!armazenamento.salvar(&p).await.unwrap());
+
+        let totais = armazenamento.sumario(0, u64::MAX).await.unwrap();
+        assert_eq!(totais, vec![("default".to_string(), 1, 1990)]);
+    }
+
+    #[tokio::test]
+    async fn sumario_respeita_intervalo_fechado() {
+        let armazenamento = ArmazenamentoMemoria::default();
+        for p in [
+            pagamento(999_999, "default", 100),
+            pagamento(1_000_000, "default", 200),
+            pagamento(2_000_000, "fallback", 300),
+            pagamento(2_000_001, "fallback", 400),
+        ] {
+            armazenamento.salvar(&p).await.unwrap();
+        }
+
+        let mut totais = armazenamento.sumario(1_000_000, 2_000_000).await.unwrap();
+        totais.sort();
+        assert_eq!(
+            totais,
+            vec![
+                ("default".to_string(), 1, 200),
+                ("fallback".to_string(), 1, 300)
+            ]
+        );
+        assert!(armazenamento.sumario(5, 4).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn busca_e_expurgo() {
+        let armazenamento = ArmazenamentoMemoria::default();
+        let p = pagamento(1_000_000, "default", 1990);
+        armazenamento.salvar(&p).await.unwrap();
+
+        let encontrado = armazenamento.buscar(&p.correlation_id).await.unwrap();
+        assert_eq!(encontrado.map(|e| e.centavos), Some(1990));
+
+        let removidos = armazenamento.expurgar(&tudo()).await.unwrap();
+        assert_eq!(removidos.ids, vec![p.correlation_id]);
+        assert!(
+            armazenamento
+                .buscar(&p.correlation_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(armazenamento.sumario(0, u64::MAX).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn compactacao_remove_so_o_que_passou_do_horizonte() {
+        let armazenamento = ArmazenamentoMemoria::default();
+        let antigo = pagamento(999_999, "default", 100);
+        let recente = pagamento(1_000_000, "default", 200);
+        armazenamento.salvar(&antigo).await.unwrap();
+        armazenamento.salvar(&recente).await.unwrap();
+
+        assert_eq!(armazenamento.compactar(1_000_000).await.unwrap(), 1);
+        assert_eq!(armazenamento.compactar(1_000_000).await.unwrap(), 0);
+        assert!(
+            armazenamento
+                .buscar(&antigo.correlation_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            armazenamento.sumario(0, u64::MAX).await.unwrap(),
+            vec![("default".to_string(), 1, 200)]
+        );
+    }
+
+    #[tokio::test]
+    async fn expurgo_filtrado_preserva_o_resto() {
+        let armazenamento = ArmazenamentoMemoria::default();
+        for p in [
+            pagamento(1_000_000, "default", 100),
+            pagamento(2_000_000, "fallback", 200),
+            pagamento(3_000_000, "default", 300),
+        ] {
+            armazenamento.salvar(&p).await.unwrap();
+        }
+
+        let filtro = FiltroExpurgo {
+            from: 0,
+            to: 2_500_000,
+            processador: Some("default".to_string()),
+        };
+        let removidos = armazenamento.expurgar(&filtro).await.unwrap();
+        assert_eq!(removidos.ids, vec![Uuid::from_u128(1_000_000)]);
+        assert_eq!(removidos.totais, vec![("default".to_string(), 1, 100)]);
+
+        let mut totais = armazenamento.sumario(0, u64::MAX).await.unwrap();
+        totais.sort();
+        assert_eq!(
+            totais,
+            vec![
+                ("default".to_string(), 1, 300),
+                ("fallback".to_string(), 1, 200)
+            ]
+        );
+    }
+}
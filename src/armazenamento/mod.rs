@@ -0,0 +1,287 @@
+pub mod memoria;
+pub mod redis;
+#[cfg(feature = "sql")]
+pub mod sql;
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use deadpool::managed::Pool;
+use deadpool_redis::{Connection, Manager, redis::RedisError};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::{
+    api,
+    appstate::AppState,
+    constantes,
+    models::payment::{PagamentoArmazenado, Payment},
+};
+
+#[derive(Debug)]
+pub struct ErroArmazenamento(pub String);
+
+impl fmt::Display for ErroArmazenamento {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<RedisError> for ErroArmazenamento {
+    fn from(e: RedisError) -> Self {
+        ErroArmazenamento(e.to_string())
+    }
+}
+
+// `backend`: "redis" (padrão), "memoria" (uma instância só) ou "sql", este com `url`
+// apontando para `sqlite://...` ou `postgres://...`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigArmazenamento {
+    pub backend: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub retencao_secs: u64,
+    pub compactacao_intervalo_secs: u64,
+}
+
+impl Default for ConfigArmazenamento {
+    fn default() -> Self {
+        Self {
+            backend: constantes::ARMAZENAMENTO.to_string(),
+            url: None,
+            retencao_secs: constantes::RETENCAO_SECS,
+            compactacao_intervalo_secs: constantes::COMPACTACAO_INTERVALO_SECS,
+        }
+    }
+}
+
+impl ConfigArmazenamento {
+    pub fn validar(&self) -> Result<(), String> {
+        match self.backend.to_ascii_lowercase().as_str() {
+            "redis" | "memoria" => {}
+            "sql" if !cfg!(feature = "sql") => {
+                return Err(
+                    "armazenamento.backend = sql, mas o binário foi compilado sem a feature `sql`"
+                        .to_string(),
+                );
+            }
+            "sql" if self.url.is_none() => {
+                return Err("armazenamento.backend = sql exige armazenamento.url".to_string());
+            }
+            "sql" => {}
+            outro => return Err(format!("backend de armazenamento desconhecido: {}", outro)),
+        }
+        if self.compactacao_intervalo_secs == 0 {
+            return Err(
+                "armazenamento.compactacao_intervalo_secs deve ser maior que zero".to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn retencao(&self) -> PoliticaRetencao {
+        PoliticaRetencao {
+            retencao_secs: self.retencao_secs,
+            intervalo_compactacao_secs: self.compactacao_intervalo_secs,
+        }
+    }
+}
+
+// Por quanto tempo os pagamentos entram no sumário. Fora do horizonte eles são
+// compactados e deixam de ser somados, em todos os backends.
+#[derive(Debug, Clone, Copy)]
+pub struct PoliticaRetencao {
+    // 0 = guarda para sempre e não compacta.
+    pub retencao_secs: u64,
+    pub intervalo_compactacao_secs: u64,
+}
+
+impl PoliticaRetencao {
+    // Menor `requestedAt` (em microssegundos) ainda dentro da retenção.
+    pub fn horizonte_micros(&self, agora_micros: u64) -> u64 {
+        if self.retencao_secs == 0 {
+            return 0;
+        }
+        agora_micros.saturating_sub(self.retencao_secs.saturating_mul(1_000_000))
+    }
+
+    // TTL de payloads e baldes no Redis. Passa da retenção em um intervalo de compactação,
+    // então o payload sempre sobrevive à sua entrada no índice e nenhum pagamento dentro
+    // do horizonte some do sumário. 0 = sem expiração.
+    pub fn ttl_secs(&self) -> u64 {
+        if self.retencao_secs == 0 {
+            return 0;
+        }
+        self.retencao_secs + self.intervalo_compactacao_secs
+    }
+}
+
+// (nome do processador, total de requisições, soma em centavos)
+pub type TotalProcessador = (String, u64, i64);
+
+pub fn acumular(totais: &mut Vec<TotalProcessador>, tipo: &str, centavos: i64) {
+    match totais.iter_mut().find(|(nome, _, _)| nome == tipo) {
+        Some((_, reqs, soma)) => {
+            *reqs += 1;
+            *soma += centavos;
+        }
+        None => totais.push((tipo.to_string(), 1, centavos)),
+    }
+}
+
+// Pagamentos com `requestedAt` em [from, to] (microssegundos) e, se informado, só os
+// de um processador.
+#[derive(Debug, Clone)]
+pub struct FiltroExpurgo {
+    pub from: u64,
+    pub to: u64,
+    pub processador: Option<String>,
+}
+
+impl FiltroExpurgo {
+    pub fn completo(&self) -> bool {
+        self.from == 0 && self.to == u64::MAX && self.processador.is_none()
+    }
+
+    pub fn aceita(&self, requested_at_micros: u64, tipo: &str) -> bool {
+        (self.from..=self.to).contains(&requested_at_micros)
+            && self.processador.as_deref().is_none_or(|p| p == tipo)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ResultadoExpurgo {
+    pub ids: Vec<Uuid>,
+    // O que saiu do sumário, por processador.
+    pub totais: Vec<TotalProcessador>,
+}
+
+// Onde os pagamentos confirmados ficam gravados. Status, dedup, fila e dead letters
+// continuam no Redis independentemente do backend escolhido aqui.
+pub trait PaymentStore: Send + Sync {
+    // Grava o pagamento se o correlationId ainda não existe. Retorna `true` só quando é
+    // novo: regravações (retry, replay) não podem contar duas vezes no sumário.
+    fn salvar<'a>(
+        &'a self,
+        pagamento: &'a PagamentoArmazenado,
+    ) -> BoxFuture<'a, Result<bool, ErroArmazenamento>>;
+
+    // Totais por processador com `requestedAt` em [from, to], em microssegundos.
+    fn sumario(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> BoxFuture<'_, Result<Vec<TotalProcessador>, ErroArmazenamento>>;
+
+    // Mesmo sumário somando pagamento a pagamento, sem agregados. Backends que não
+    // mantêm agregados respondem igual a `sumario`.
+    fn sumario_varredura(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> BoxFuture<'_, Result<Vec<TotalProcessador>, ErroArmazenamento>> {
+        self.sumario(from, to)
+    }
+
+    fn buscar<'a>(
+        &'a self,
+        id: &'a Uuid,
+    ) -> BoxFuture<'a, Result<Option<PagamentoArmazenado>, ErroArmazenamento>>;
+
+    // Remove os pagamentos do filtro junto com a parte deles nos agregados, deixando o
+    // sumário do que sobrou consistente.
+    fn expurgar<'a>(
+        &'a self,
+        filtro: &'a FiltroExpurgo,
+    ) -> BoxFuture<'a, Result<ResultadoExpurgo, ErroArmazenamento>>;
+
+    // Remove um lote de pagamentos com `requestedAt` anterior a `antes_de` (microssegundos).
+    // Retorna quantos itens saíram; o chamador repete até voltar 0.
+    fn compactar(&self, antes_de: u64) -> BoxFuture<'_, Result<u64, ErroArmazenamento>>;
+}
+
+pub async fn criar_armazenamento(
+    config: &ConfigArmazenamento,
+    redis_pool: Pool<Manager, Connection>,
+) -> Result<Arc<dyn PaymentStore>, String> {
+    match config.backend.to_ascii_lowercase().as_str() {
+        "redis" => Ok(Arc::new(redis::ArmazenamentoRedis::new(
+            redis_pool,
+            config.retencao().ttl_secs(),
+        ))),
+        "memoria" => Ok(Arc::new(memoria::ArmazenamentoMemoria::default())),
+        #[cfg(feature = "sql")]
+        "sql" => {
+            let url = config
+                .url
+                .as_deref()
+                .ok_or("armazenamento.backend = sql exige armazenamento.url")?;
+            let armazenamento = sql::ArmazenamentoSql::conectar(url)
+                .await
+                .map_err(|e| format!("falha ao abrir {}: {}", url, e))?;
+            Ok(Arc::new(armazenamento))
+        }
+        outro => Err(format!("backend de armazenamento indisponível: {}", outro)),
+    }
+}
+
+// Tenta gravar até conseguir uma resposta do backend (pool esgotado, conexão caída) e
+// então marca o status como Confirmado. Retorna se o pagamento era novo.
+#[tracing::instrument(name = "gravacao", skip_all)]
+pub async fn salvar_pagamento(
+    state: &AppState,
+    pagamento: &Payment,
+) -> Result<bool, ErroArmazenamento> {
+    let max_tentativas = 50u8;
+    let retry_delay = Duration::from_millis(1);
+    let armazenado = pagamento.to_armazenado();
+
+    let cronometro = state.metricas.gravacao.start_timer();
+    let mut resultado = Err(ErroArmazenamento("nenhuma tentativa".to_string()));
+    for _ in 1..=max_tentativas {
+        resultado = state.armazenamento.salvar(&armazenado).await;
+        match &resultado {
+            Ok(novo) => {
+                debug!(novo, "pagamento gravado");
+                break;
+            }
+            Err(e) => debug!(erro = %e, "falha ao gravar, tentando de novo"),
+        }
+        tokio::time::sleep(retry_delay).await;
+    }
+    cronometro.observe_duration();
+
+    let novo = resultado?;
+    if let Err(e) = api::redis::registrar_confirmado(state, pagamento).await {
+        warn!(erro = %e, "pagamento gravado, mas o status não foi atualizado");
+    }
+    Ok(novo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retencao_zero_guarda_tudo() {
+        let retencao = PoliticaRetencao {
+            retencao_secs: 0,
+            intervalo_compactacao_secs: 60,
+        };
+        assert_eq!(retencao.horizonte_micros(5_000_000), 0);
+        assert_eq!(retencao.ttl_secs(), 0);
+    }
+
+    #[test]
+    fn ttl_cobre_o_horizonte_mais_um_intervalo() {
+        let retencao = PoliticaRetencao {
+            retencao_secs: 3600,
+            intervalo_compactacao_secs: 60,
+        };
+        assert_eq!(retencao.horizonte_micros(3_601_000_000), 1_000_000);
+        assert_eq!(retencao.horizonte_micros(10), 0);
+        assert_eq!(retencao.ttl_secs(), 3660);
+    }
+}
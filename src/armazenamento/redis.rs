@@ -0,0 +1,412 @@
+use deadpool::managed::Pool;
+use deadpool_redis::{
+    Connection, Manager,
+    redis::{self, ErrorKind, RedisError, Script},
+};
+use futures::future::BoxFuture;
+use uuid::Uuid;
+
+use crate::{
+    armazenamento::{
+        ErroArmazenamento, FiltroExpurgo, PaymentStore, ResultadoExpurgo, TotalProcessador,
+    },
+    constantes,
+    models::payment::PagamentoArmazenado,
+};
+
+// Payload em `payment:{id}`, índice por data em `payments_by_date` e um hash por segundo
+// em `summary:{segundo}` com `{processador}:reqs` e `{processador}:cents`.
+// Payloads e baldes expiram em `ttl_secs` (0 = nunca); as entradas dos índices saem na
+// compactação, antes disso.
+pub struct ArmazenamentoRedis {
+    pool: Pool<Manager, Connection>,
+    ttl_secs: u64,
+    script_salvar: Script,
+    script_sumario: Script,
+    script_expurgar: Script,
+    script_compactar: Script,
+}
+
+impl ArmazenamentoRedis {
+    pub fn new(pool: Pool<Manager, Connection>, ttl_secs: u64) -> Self {
+        Self {
+            pool,
+            ttl_secs,
+            // O SET NX do payload decide se o pagamento é novo; só então entra no índice e
+            // no balde do segundo.
+            script_salvar: Script::new(
+                r#"
+                local ttl = tonumber(ARGV[3])
+                local novo
+                if ttl > 0 then
+                    novo = redis.call('SET', KEYS[1], ARGV[1], 'NX', 'EX', ttl)
+                else
+                    novo = redis.call('SET', KEYS[1], ARGV[1], 'NX')
+                end
+                if not novo then
+                    return 0
+                end
+                redis.call('ZADD', KEYS[2], ARGV[2], KEYS[1])
+                redis.call('HINCRBY', KEYS[3], ARGV[4] .. ':reqs', 1)
+                redis.call('HINCRBY', KEYS[3], ARGV[4] .. ':cents', ARGV[5])
+                if ttl > 0 then
+                    redis.call('EXPIRE', KEYS[3], ttl)
+                end
+                redis.call('ZADD', KEYS[4], ARGV[6], ARGV[6])
+                return 1
+            "#,
+            ),
+            script_sumario: Script::new(SCRIPT_SUMARIO),
+            script_expurgar: Script::new(SCRIPT_EXPURGAR),
+            // Um lote por chamada para não segurar o Redis: pagamentos com score abaixo de
+            // ARGV[1] (microssegundos) e baldes abaixo de ARGV[2] (segundos). Também apaga as
+            // chaves, cobrindo as que foram gravadas sem TTL.
+            script_compactar: Script::new(
+                r#"
+                local lote = tonumber(ARGV[4])
+                local function compacta(indice, prefixo, limite)
+                    local membros = redis.call('ZRANGEBYSCORE', indice, '-inf', '(' .. limite,
+                        'LIMIT', 0, lote)
+                    if #membros == 0 then
+                        return 0
+                    end
+                    local chaves = {}
+                    for i, membro in ipairs(membros) do
+                        chaves[i] = prefixo .. membro
+                    end
+                    redis.call('DEL', unpack(chaves))
+                    redis.call('ZREM', indice, unpack(membros))
+                    return #membros
+                end
+                return compacta(KEYS[1], '', ARGV[1]) + compacta(KEYS[2], ARGV[3], ARGV[2])
+            "#,
+            ),
+        }
+    }
+
+    async fn conexao(&self) -> Result<Connection, RedisError> {
+        self.pool.get().await.map_err(|e| {
+            RedisError::from((ErrorKind::IoError, "pool Redis indisponível", e.to_string()))
+        })
+    }
+
+    async fn coletar_sumario(
+        &self,
+        from: u64,
+        to: u64,
+        somente_varredura: bool,
+    ) -> Result<Vec<TotalProcessador>, ErroArmazenamento> {
+        let mut conn = self.conexao().await?;
+        let totais: Vec<TotalProcessador> = self
+            .script_sumario
+            .key(constantes::PAGAMENTOS_POR_DATA)
+            .key(constantes::BALDES_POR_SEGUNDO)
+            .arg(from)
+            .arg(to)
+            .arg(constantes::PREFIXO_BALDE)
+            .arg(if somente_varredura { "1" } else { "0" })
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(totais)
+    }
+}
+
+impl PaymentStore for ArmazenamentoRedis {
+    fn salvar<'a>(
+        &'a self,
+        pagamento: &'a PagamentoArmazenado,
+    ) -> BoxFuture<'a, Result<bool, ErroArmazenamento>> {
+        Box::pin(async move {
+            let json = simd_json::to_string(pagamento)
+                .map_err(|e| ErroArmazenamento(format!("pagamento inválido: {}", e)))?;
+            let tempo = pagamento.requested_at.timestamp_micros() as u64;
+            let segundo = tempo / 1_000_000;
+            let mut conn = self.conexao().await?;
+
+            let novo: u8 = self
+                .script_salvar
+                .key(format!("payment:{}", pagamento.correlation_id))
+                .key(constantes::PAGAMENTOS_POR_DATA)
+                .key(format!("{}{}", constantes::PREFIXO_BALDE, segundo))
+                .key(constantes::BALDES_POR_SEGUNDO)
+                .arg(json)
+                .arg(tempo)
+                .arg(self.ttl_secs)
+                .arg(&pagamento.tipo)
+                .arg(pagamento.centavos)
+                .arg(segundo)
+                .invoke_async(&mut conn)
+                .await?;
+            Ok(novo == 1)
+        })
+    }
+
+    // Segundos inteiros dentro de [from, to] vêm dos baldes pré-agregados; só as pontas
+    // fracionárias são varridas pagamento a pagamento.
+    fn sumario(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> BoxFuture<'_, Result<Vec<TotalProcessador>, ErroArmazenamento>> {
+        Box::pin(self.coletar_sumario(from, to, false))
+    }
+
+    // O(n): serve só para conferir que os baldes batem com os pagamentos individuais.
+    fn sumario_varredura(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> BoxFuture<'_, Result<Vec<TotalProcessador>, ErroArmazenamento>> {
+        Box::pin(self.coletar_sumario(from, to, true))
+    }
+
+    fn buscar<'a>(
+        &'a self,
+        id: &'a Uuid,
+    ) -> BoxFuture<'a, Result<Option<PagamentoArmazenado>, ErroArmazenamento>> {
+        Box::pin(async move {
+            let mut conn = self.conexao().await?;
+            let valor: Option<String> = redis::cmd("GET")
+                .arg(format!("payment:{}", id))
+                .query_async(&mut conn)
+                .await?;
+            Ok(valor.and_then(|json| serde_json::from_str(&json).ok()))
+        })
+    }
+
+    fn expurgar<'a>(
+        &'a self,
+        filtro: &'a FiltroExpurgo,
+    ) -> BoxFuture<'a, Result<ResultadoExpurgo, ErroArmazenamento>> {
+        Box::pin(async move {
+            let mut conn = self.conexao().await?;
+            let mut resultado = ResultadoExpurgo {
+                ids: Vec::new(),
+                totais: Vec::new(),
+            };
+            let mut posicao = 0u64;
+            loop {
+                let (ids, totais, proxima, continua): (
+                    Vec<String>,
+                    Vec<TotalProcessador>,
+                    u64,
+                    u8,
+                ) = self
+                    .script_expurgar
+                    .key(constantes::PAGAMENTOS_POR_DATA)
+                    .key(constantes::BALDES_POR_SEGUNDO)
+                    .arg(filtro.from)
+                    .arg(filtro.to)
+                    .arg(filtro.processador.as_deref().unwrap_or_default())
+                    .arg(constantes::PREFIXO_BALDE)
+                    .arg(if filtro.completo() { "1" } else { "0" })
+                    .arg(posicao)
+                    .arg(1000)
+                    .invoke_async(&mut conn)
+                    .await?;
+
+                resultado
+                    .ids
+                    .extend(ids.iter().filter_map(|id| Uuid::parse_str(id).ok()));
+                for (tipo, reqs, centavos) in totais {
+                    match resultado
+                        .totais
+                        .iter_mut()
+                        .find(|(nome, _, _)| *nome == tipo)
+                    {
+                        Some((_, total_reqs, soma)) => {
+                            *total_reqs += reqs;
+                            *soma += centavos;
+                        }
+                        None => resultado.totais.push((tipo, reqs, centavos)),
+                    }
+                }
+                if continua == 0 {
+                    return Ok(resultado);
+                }
+                posicao = proxima;
+                tokio::task::yield_now().await;
+            }
+        })
+    }
+
+    fn compactar(&self, antes_de: u64) -> BoxFuture<'_, Result<u64, ErroArmazenamento>> {
+        Box::pin(async move {
+            let mut conn = self.conexao().await?;
+            // O balde do segundo do horizonte tem pagamentos dos dois lados; fica.
+            let removidos: u64 = self
+                .script_compactar
+                .key(constantes::PAGAMENTOS_POR_DATA)
+                .key(constantes::BALDES_POR_SEGUNDO)
+                .arg(antes_de)
+                .arg(antes_de / 1_000_000)
+                .arg(constantes::PREFIXO_BALDE)
+                .arg(1000)
+                .invoke_async(&mut conn)
+                .await?;
+            Ok(removidos)
+        })
+    }
+}
+
+const SCRIPT_SUMARIO: &str = r#"
+    local from = tonumber(ARGV[1])
+    local to = tonumber(ARGV[2])
+    local reqs = {}
+    local cents = {}
+    local nomes = {}
+
+    -- Centavos são inteiros: a soma em double é exata até 2^53
+    local function acumula(tipo, qtd, valor)
+        if reqs[tipo] == nil then
+            reqs[tipo] = 0
+            cents[tipo] = 0
+            table.insert(nomes, tipo)
+        end
+        reqs[tipo] = reqs[tipo] + qtd
+        cents[tipo] = cents[tipo] + valor
+    end
+
+    -- Varredura exata de [de, ate] em microssegundos
+    local function varre(de, ate)
+        if de > ate then
+            return
+        end
+        local keys = redis.call('ZRANGEBYSCORE', KEYS[1],
+            string.format('%.0f', de), string.format('%.0f', ate))
+
+        -- Processa as chaves em lotes de 3000 para evitar limites do Lua
+        local chunk_size = 3000
+        for i = 1, #keys, chunk_size do
+            local chunk_keys = {}
+            for j = i, math.min(i + chunk_size - 1, #keys) do
+                table.insert(chunk_keys, keys[j])
+            end
+
+            local values = redis.call('MGET', unpack(chunk_keys))
+
+            for _, json_str in ipairs(values) do
+                if json_str then
+                    -- cjson é o parser de JSON embutido no Redis
+                    local data = cjson.decode(json_str)
+                    if type(data.tipo) == 'string' then
+                        acumula(data.tipo, 1, data.centavos)
+                    end
+                end
+            end
+        end
+    end
+
+    -- Segundo s cobre [s * 1e6, s * 1e6 + 999999]
+    local primeiro = math.ceil(from / 1000000)
+    local ultimo = math.floor((to - 999999) / 1000000)
+
+    if ARGV[4] == '1' or primeiro > ultimo then
+        varre(from, to)
+    else
+        varre(from, primeiro * 1000000 - 1)
+        local baldes = redis.call('ZRANGEBYSCORE', KEYS[2],
+            string.format('%.0f', primeiro), string.format('%.0f', ultimo))
+        for _, segundo in ipairs(baldes) do
+            local campos = redis.call('HGETALL', ARGV[3] .. segundo)
+            for i = 1, #campos, 2 do
+                local tipo, metrica = string.match(campos[i], '^(.*):(%a+)$')
+                if metrica == 'reqs' then
+                    acumula(tipo, tonumber(campos[i + 1]), 0)
+                elseif metrica == 'cents' then
+                    acumula(tipo, 0, tonumber(campos[i + 1]))
+                end
+            end
+        end
+        varre((ultimo + 1) * 1000000, to)
+    end
+
+    local resultado = {}
+    for _, tipo in ipairs(nomes) do
+        table.insert(resultado, {tipo, reqs[tipo], cents[tipo]})
+    end
+    return resultado
+"#;
+
+// Um lote por chamada, como a compactação: olha até ARGV[7] entradas do índice a partir
+// da posição ARGV[6] e remove as do filtro (ARGV[3] vazio = todos os processadores),
+// descontando cada uma do balde do seu segundo. As que ficam deslocam a posição da
+// próxima chamada. Esgotado o índice, no expurgo completo (ARGV[5] == '1') os baldes que
+// sobrarem, só de pagamentos já expirados, saem também em lotes. Devolve
+// {ids, totais, próxima posição, 1 se ainda há o que fazer}.
+const SCRIPT_EXPURGAR: &str = r#"
+    local prefixo_pagamento = 'payment:'
+    local offset = tonumber(ARGV[6])
+    local lote = tonumber(ARGV[7])
+    local ids = {}
+    local reqs = {}
+    local cents = {}
+    local nomes = {}
+
+    local function resultado(continua)
+        local totais = {}
+        for _, tipo in ipairs(nomes) do
+            table.insert(totais, {tipo, reqs[tipo], cents[tipo]})
+        end
+        return {ids, totais, offset, continua}
+    end
+
+    local membros = redis.call('ZRANGEBYSCORE', KEYS[1], ARGV[1], ARGV[2],
+        'WITHSCORES', 'LIMIT', offset, lote)
+    if #membros > 0 then
+        local chaves = {}
+        for i = 1, #membros, 2 do
+            table.insert(chaves, membros[i])
+        end
+        local valores = redis.call('MGET', unpack(chaves))
+
+        for i, chave in ipairs(chaves) do
+            local data = valores[i] and cjson.decode(valores[i])
+            local tipo = data and data.tipo
+            if ARGV[3] == '' or tipo == ARGV[3] then
+                redis.call('DEL', chave)
+                redis.call('ZREM', KEYS[1], chave)
+                table.insert(ids, string.sub(chave, #prefixo_pagamento + 1))
+
+                if data then
+                    local segundo = string.format('%.0f',
+                        math.floor(tonumber(membros[i * 2]) / 1000000))
+                    local balde = ARGV[4] .. segundo
+                    if redis.call('HINCRBY', balde, tipo .. ':reqs', -1) <= 0 then
+                        redis.call('HDEL', balde, tipo .. ':reqs', tipo .. ':cents')
+                    else
+                        redis.call('HINCRBY', balde, tipo .. ':cents', -data.centavos)
+                    end
+                    if redis.call('EXISTS', balde) == 0 then
+                        redis.call('ZREM', KEYS[2], segundo)
+                    end
+
+                    if reqs[tipo] == nil then
+                        reqs[tipo] = 0
+                        cents[tipo] = 0
+                        table.insert(nomes, tipo)
+                    end
+                    reqs[tipo] = reqs[tipo] + 1
+                    cents[tipo] = cents[tipo] + data.centavos
+                end
+            else
+                offset = offset + 1
+            end
+        end
+        return resultado(1)
+    end
+
+    if ARGV[5] == '1' then
+        local segundos = redis.call('ZRANGE', KEYS[2], 0, lote - 1)
+        if #segundos > 0 then
+            local chaves = {}
+            for i, segundo in ipairs(segundos) do
+                chaves[i] = ARGV[4] .. segundo
+            end
+            redis.call('DEL', unpack(chaves))
+            redis.call('ZREM', KEYS[2], unpack(segundos))
+            return resultado(1)
+        end
+    end
+    return resultado(0)
+"#;
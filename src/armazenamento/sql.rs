@@ -0,0 +1,242 @@
+use chrono::DateTime;
+use futures::future::BoxFuture;
+use sqlx::{AnyPool, Row, any::AnyPoolOptions};
+use uuid::Uuid;
+
+use crate::{
+    armazenamento::{
+        ErroArmazenamento, FiltroExpurgo, PaymentStore, ResultadoExpurgo, TotalProcessador,
+        acumular,
+    },
+    models::payment::PagamentoArmazenado,
+};
+
+impl From<sqlx::Error> for ErroArmazenamento {
+    fn from(e: sqlx::Error) -> Self {
+        ErroArmazenamento(e.to_string())
+    }
+}
+
+// SQLite ou Postgres, conforme o esquema da URL. O SQL abaixo é o subconjunto comum aos
+// dois: `$n` nos parâmetros, `ON CONFLICT DO NOTHING` e só tipos inteiros e texto.
+pub struct ArmazenamentoSql {
+    pool: AnyPool,
+}
+
+impl ArmazenamentoSql {
+    pub async fn conectar(url: &str) -> Result<Self, sqlx::Error> {
+        sqlx::any::install_default_drivers();
+        let mut opcoes = AnyPoolOptions::new();
+        // Cada conexão com `sqlite::memory:` abriria um banco diferente.
+        if url.starts_with("sqlite::memory:") {
+            opcoes = opcoes.max_connections(1);
+        }
+        let pool = opcoes.connect(url).await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS pagamentos (
+                correlation_id TEXT PRIMARY KEY,
+                centavos BIGINT NOT NULL,
+                requested_at BIGINT NOT NULL,
+                tipo TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS pagamentos_por_data ON pagamentos (requested_at)")
+            .execute(&pool)
+            .await?;
+
+        Ok(Self { pool })
+    }
+}
+
+// `requested_at` é BIGINT com sinal; o intervalo aberto (`u64::MAX`) vira o maior i64.
+fn micros_sql(micros: u64) -> i64 {
+    i64::try_from(micros).unwrap_or(i64::MAX)
+}
+
+impl PaymentStore for ArmazenamentoSql {
+    fn salvar<'a>(
+        &'a self,
+        pagamento: &'a PagamentoArmazenado,
+    ) -> BoxFuture<'a, Result<bool, ErroArmazenamento>> {
+        Box::pin(async move {
+            let resultado = sqlx::query(
+                "INSERT INTO pagamentos (correlation_id, centavos, requested_at, tipo)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (correlation_id) DO NOTHING",
+            )
+            .bind(pagamento.correlation_id.to_string())
+            .bind(pagamento.centavos)
+            .bind(pagamento.requested_at.timestamp_micros())
+            .bind(&pagamento.tipo)
+            .execute(&self.pool)
+            .await?;
+            Ok(resultado.rows_affected() == 1)
+        })
+    }
+
+    fn sumario(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> BoxFuture<'_, Result<Vec<TotalProcessador>, ErroArmazenamento>> {
+        Box::pin(async move {
+            // No Postgres SUM(BIGINT) é NUMERIC; o CAST mantém o mesmo tipo nos dois bancos.
+            let linhas = sqlx::query(
+                "SELECT tipo, COUNT(*), CAST(SUM(centavos) AS BIGINT)
+                 FROM pagamentos
+                 WHERE requested_at BETWEEN $1 AND $2
+                 GROUP BY tipo",
+            )
+            .bind(micros_sql(from))
+            .bind(micros_sql(to))
+            .fetch_all(&self.pool)
+            .await?;
+
+            linhas
+                .iter()
+                .map(|linha| {
+                    let reqs: i64 = linha.try_get(1)?;
+                    Ok((linha.try_get(0)?, reqs as u64, linha.try_get(2)?))
+                })
+                .collect()
+        })
+    }
+
+    fn buscar<'a>(
+        &'a self,
+        id: &'a Uuid,
+    ) -> BoxFuture<'a, Result<Option<PagamentoArmazenado>, ErroArmazenamento>> {
+        Box::pin(async move {
+            let linha = sqlx::query(
+                "SELECT centavos, requested_at, tipo FROM pagamentos WHERE correlation_id = $1",
+            )
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+
+            let Some(linha) = linha else {
+                return Ok(None);
+            };
+            let requested_at = DateTime::from_timestamp_micros(linha.try_get(1)?)
+                .ok_or_else(|| ErroArmazenamento("requested_at fora do intervalo".to_string()))?;
+            Ok(Some(PagamentoArmazenado {
+                correlation_id: *id,
+                centavos: linha.try_get(0)?,
+                requested_at,
+                tipo: linha.try_get(2)?,
+            }))
+        })
+    }
+
+    fn expurgar<'a>(
+        &'a self,
+        filtro: &'a FiltroExpurgo,
+    ) -> BoxFuture<'a, Result<ResultadoExpurgo, ErroArmazenamento>> {
+        Box::pin(async move {
+            // Processador vazio casa com todos.
+            let linhas = sqlx::query(
+                "DELETE FROM pagamentos
+                 WHERE requested_at BETWEEN $1 AND $2 AND ($3 = '' OR tipo = $3)
+                 RETURNING correlation_id, tipo, centavos",
+            )
+            .bind(micros_sql(filtro.from))
+            .bind(micros_sql(filtro.to))
+            .bind(filtro.processador.clone().unwrap_or_default())
+            .fetch_all(&self.pool)
+            .await?;
+
+            let mut resultado = ResultadoExpurgo::default();
+            for linha in &linhas {
+                let id: String = linha.try_get(0)?;
+                let tipo: String = linha.try_get(1)?;
+                resultado.ids.push(
+                    Uuid::parse_str(&id)
+                        .map_err(|e| ErroArmazenamento(format!("id inválido {}: {}", id, e)))?,
+                );
+                acumular(&mut resultado.totais, &tipo, linha.try_get(2)?);
+            }
+            Ok(resultado)
+        })
+    }
+
+    fn compactar(&self, antes_de: u64) -> BoxFuture<'_, Result<u64, ErroArmazenamento>> {
+        Box::pin(async move {
+            let resultado = sqlx::query("DELETE FROM pagamentos WHERE requested_at < $1")
+                .bind(micros_sql(antes_de))
+                .execute(&self.pool)
+                .await?;
+            Ok(resultado.rows_affected())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn sqlite_em_memoria_grava_soma_e_expurga() {
+        let armazenamento = ArmazenamentoSql::conectar("sqlite::memory:").await.unwrap();
+        let p = PagamentoArmazenado {
+            correlation_id: Uuid::from_u128(1),
+            centavos: 1990,
+            requested_at: DateTime::from_timestamp_micros(1_500_000).unwrap(),
+            tipo: "default".to_string(),
+        };
+        assert!(armazenamento.salvar(&p).await.unwrap());
+        assert!(!armazenamento.salvar(&p).await.unwrap());
+
+        let totais = armazenamento.sumario(0, u64::MAX).await.unwrap();
+        assert_eq!(totais, vec![("default".to_string(), 1, 1990)]);
+        assert!(
+            armazenamento
+                .sumario(0, 1_499_999)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let encontrado = armazenamento
+            .buscar(&p.correlation_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(encontrado.requested_at, p.requested_at);
+
+        assert_eq!(armazenamento.compactar(1_500_000).await.unwrap(), 0);
+        assert_eq!(armazenamento.compactar(1_500_001).await.unwrap(), 1);
+        assert!(armazenamento.salvar(&p).await.unwrap());
+
+        let filtro = FiltroExpurgo {
+            from: 0,
+            to: u64::MAX,
+            processador: Some("fallback".to_string()),
+        };
+        assert!(
+            armazenamento
+                .expurgar(&filtro)
+                .await
+                .unwrap()
+                .ids
+                .is_empty()
+        );
+
+        let filtro = FiltroExpurgo {
+            processador: Some("default".to_string()),
+            ..filtro
+        };
+        let removidos = armazenamento.expurgar(&filtro).await.unwrap();
+        assert_eq!(removidos.ids, vec![p.correlation_id]);
+        assert_eq!(removidos.totais, vec![("default".to_string(), 1, 1990)]);
+        assert!(
+            armazenamento
+                .buscar(&p.correlation_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{configuracao::duracao_ms, constantes};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigCircuito {
+    pub falhas_consecutivas: u32,
+    // Fração de falhas na janela que abre o circuito, avaliada só com `minimo_amostras`.
+    pub taxa_erro_maxima: f64,
+    pub tamanho_janela: usize,
+    pub minimo_amostras: usize,
+    #[serde(rename = "tempo_aberto_ms", with = "duracao_ms")]
+    pub tempo_aberto: Duration,
+    // Requisições de teste simultâneas no meio-aberto; todas precisam ter sucesso para fechar.
+    pub tentativas_meio_aberto: u32,
+}
+
+impl Default for ConfigCircuito {
+    fn default() -> Self {
+        Self {
+            falhas_consecutivas: constantes::CB_FALHAS_CONSECUTIVAS,
+            taxa_erro_maxima: constantes::CB_TAXA_ERRO,
+            tamanho_janela: constantes::CB_JANELA,
+            minimo_amostras: constantes::CB_MIN_AMOSTRAS,
+            tempo_aberto: Duration::from_millis(constantes::CB_TEMPO_ABERTO_MS),
+            tentativas_meio_aberto: constantes::CB_TENTATIVAS_MEIO_ABERTO,
+        }
+    }
+}
+
+impl ConfigCircuito {
+    pub fn validar(&self) -> Result<(), String> {
+        if self.falhas_consecutivas == 0 || self.tentativas_meio_aberto == 0 {
+            return Err(
+                "circuito.falhas_consecutivas e tentativas_meio_aberto devem ser maiores que zero"
+                    .to_string(),
+            );
+        }
+        if !(self.taxa_erro_maxima > 0.0 && self.taxa_erro_maxima <= 1.0) {
+            return Err("circuito.taxa_erro_maxima deve ficar em (0, 1]".to_string());
+        }
+        // Com mais amostras mínimas do que cabem na janela, a taxa nunca seria avaliada.
+        if self.minimo_amostras == 0 || self.minimo_amostras > self.tamanho_janela {
+            return Err("circuito.minimo_amostras deve ficar entre 1 e tamanho_janela".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EstadoCircuito {
+    Fechado,
+    Aberto { desde: Instant },
+    MeioAberto { em_teste: u32, sucessos: u32 },
+}
+
+impl EstadoCircuito {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EstadoCircuito::Fechado => "Fechado",
+            EstadoCircuito::Aberto { .. } => "Aberto",
+            EstadoCircuito::MeioAberto { .. } => "MeioAberto",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: ConfigCircuito,
+    estado: EstadoCircuito,
+    // true = falha. Só guarda resultados com o circuito fechado.
+    janela: VecDeque<bool>,
+    falhas_consecutivas: u32,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: ConfigCircuito) -> Self {
+        Self {
+            config,
+            estado: EstadoCircuito::Fechado,
+            janela: VecDeque::with_capacity(config.tamanho_janela),
+            falhas_consecutivas: 0,
+        }
+    }
+
+    pub fn estado(&self) -> EstadoCircuito {
+        self.estado
+    }
+
+    // Consulta sem efeito colateral, usada na fotografia do roteamento.
+    pub fn disponivel(&self, agora: Instant) -> bool {
+        match self.estado {
+            EstadoCircuito::Fechado => true,
+            EstadoCircuito::Aberto { desde } => {
+                agora.duration_since(desde) >= self.config.tempo_aberto
+            }
+            EstadoCircuito::MeioAberto { em_teste, .. } => {
+                em_teste < self.config.tentativas_meio_aberto
+            }
+        }
+    }
+
+    // Reserva a passagem de uma requisição. No meio-aberto ocupa um dos slots de teste,
+    // que é liberado por `registrar_sucesso`/`registrar_falha`.
+    pub fn permitir(&mut self, agora: Instant) -> bool {
+        if let EstadoCircuito::Aberto { desde } = self.estado
+            && agora.duration_since(desde) >= self.config.tempo_aberto
+        {
+            self.estado = EstadoCircuito::MeioAberto {
+                em_teste: 0,
+                sucessos: 0,
+            };
+        }
+
+        match &mut self.estado {
+            EstadoCircuito::Fechado => true,
+            EstadoCircuito::Aberto { .. } => false,
+            EstadoCircuito::MeioAberto { em_teste, .. } => {
+                if *em_teste < self.config.tentativas_meio_aberto {
+                    *em_teste += 1;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    pub fn registrar_sucesso(&mut self) {
+        self.falhas_consecutivas = 0;
+        match &mut self.estado {
+            EstadoCircuito::Fechado => self.empurrar(false),
+            EstadoCircuito::MeioAberto { sucessos, .. } => {
+                *sucessos += 1;
+                if *sucessos >= self.config.tentativas_meio_aberto {
+                    self.fechar();
+                }
+            }
+            // Resposta de uma requisição liberada antes de o circuito abrir.
+            EstadoCircuito::Aberto { .. } => {}
+        }
+    }
+
+    pub fn registrar_falha(&mut self, agora: Instant) {
+        self.falhas_consecutivas += 1;
+        match self.estado {
+            EstadoCircuito::Fechado => {
+                self.empurrar(true);
+                if self.falhas_consecutivas >= self.config.falhas_consecutivas
+                    || self.taxa_erro_excedida()
+                {
+                    self.abrir(agora);
+                }
+            }
+            EstadoCircuito::MeioAberto { .. } => self.abrir(agora),
+            EstadoCircuito::Aberto { .. } => {}
+        }
+    }
+
+    fn empurrar(&mut self, falha: bool) {
+        if self.janela.len() >= self.config.tamanho_janela {
+            self.janela.pop_front();
+        }
+        self.janela.push_back(falha);
+    }
+
+    fn taxa_erro_excedida(&self) -> bool {
+        if self.janela.len() < self.config.minimo_amostras.max(1) {
+            return false;
+        }
+        let falhas = self.janela.iter().filter(|falha| **falha).count();
+        falhas as f64 / self.janela.len() as f64 >= self.config.taxa_erro_maxima
+    }
+
+    fn abrir(&mut self, agora: Instant) {
+        self.estado = EstadoCircuito::Aberto { desde: agora };
+        self.janela.clear();
+    }
+
+    fn fechar(&mut self) {
+        self.estado = EstadoCircuito::Fechado;
+        self.janela.clear();
+        self.falhas_consecutivas = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ConfigCircuito {
+        ConfigCircuito {
+            falhas_consecutivas: 3,
+            taxa_erro_maxima: 0.5,
+            tamanho_janela: 10,
+            minimo_amostras: 6,
+            tempo_aberto: Duration::from_millis(100),
+            tentativas_meio_aberto: 2,
+        }
+    }
+
+    #[test]
+    fn abre_apos_falhas_consecutivas() {
+        let agora = Instant::now();
+        let mut cb = CircuitBreaker::new(config());
+        cb.registrar_falha(agora);
+        cb.registrar_falha(agora);
+        assert_eq!(cb.estado(), EstadoCircuito::Fechado);
+        cb.registrar_falha(agora);
+        assert_eq!(cb.estado(), EstadoCircuito::Aberto { desde: agora });
+        assert!(!cb.permitir(agora));
+    }
+
+    #[test]
+    fn abre_pela_taxa_de_erro_intercalada() {
+        let agora = Instant::now();
+        let mut cb = CircuitBreaker::new(config());
+        for _ in 0..3 {
+            cb.registrar_sucesso();
+            cb.registrar_falha(agora);
+        }
+        assert!(matches!(cb.estado(), EstadoCircuito::Aberto { .. }));
+    }
+
+    #[test]
+    fn meio_aberto_limita_tentativas_e_fecha_com_sucessos() {
+        let inicio = Instant::now();
+        let mut cb = CircuitBreaker::new(config());
+        for _ in 0..3 {
+            cb.registrar_falha(inicio);
+        }
+
+        let depois = inicio + Duration::from_millis(150);
+        assert!(cb.disponivel(depois));
+        assert!(cb.permitir(depois));
+        assert!(cb.permitir(depois));
+        assert!(!cb.permitir(depois));
+
+        cb.registrar_sucesso();
+        assert!(matches!(cb.estado(), EstadoCircuito::MeioAberto { .. }));
+        cb.registrar_sucesso();
+        assert_eq!(cb.estado(), EstadoCircuito::Fechado);
+    }
+
+    #[test]
+    fn falha_no_meio_aberto_reabre() {
+        let inicio = Instant::now();
+        let mut cb = CircuitBreaker::new(config());
+        for _ in 0..3 {
+            cb.registrar_falha(inicio);
+        }
+
+        let depois = inicio + Duration::from_millis(150);
+        assert!(cb.permitir(depois));
+        cb.registrar_falha(depois);
+        assert_eq!(cb.estado(), EstadoCircuito::Aberto { desde: depois });
+    }
+}
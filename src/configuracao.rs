@@ -0,0 +1,904 @@
+use std::{
+    env,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize, Serializer};
+use tracing_subscriber::EnvFilter;
+
+use crate::{
+    api::fila::BackendFila,
+    armazenamento::ConfigArmazenamento,
+    circuit_breaker::ConfigCircuito,
+    constantes,
+    estatisticas::ConfigEstatisticas,
+    lideranca::ConfigLideranca,
+    models::processor::{self, ConfigProcessador, ConfigSaude},
+    roteamento,
+};
+
+// Toda a configuração do processo. Ordem de precedência: padrões (`constantes`), arquivo
+// TOML e variáveis de ambiente, que continuam com os nomes de antes do arquivo existir.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Configuracao {
+    pub servidor: ConfigServidor,
+    pub fila: ConfigFila,
+    pub redis: ConfigRedis,
+    pub nats: ConfigNats,
+    pub pagamentos: ConfigPagamentos,
+    pub roteamento: ConfigRoteamento,
+    pub circuito: ConfigCircuito,
+    pub saude: ConfigSaude,
+    pub lideranca: ConfigLideranca,
+    pub estatisticas: ConfigEstatisticas,
+    pub armazenamento: ConfigArmazenamento,
+    pub encerramento: ConfigEncerramento,
+    pub lote: ConfigLote,
+    pub admissao: ConfigAdmissao,
+    pub log: ConfigLog,
+    pub processadores: Vec<ConfigProcessador>,
+}
+
+impl Default for Configuracao {
+    fn default() -> Self {
+        Self {
+            servidor: ConfigServidor::default(),
+            fila: ConfigFila::default(),
+            redis: ConfigRedis::default(),
+            nats: ConfigNats::default(),
+            pagamentos: ConfigPagamentos::default(),
+            roteamento: ConfigRoteamento::default(),
+            circuito: ConfigCircuito::default(),
+            saude: ConfigSaude::default(),
+            lideranca: ConfigLideranca::default(),
+            estatisticas: ConfigEstatisticas::default(),
+            armazenamento: ConfigArmazenamento::default(),
+            encerramento: ConfigEncerramento::default(),
+            lote: ConfigLote::default(),
+            admissao: ConfigAdmissao::default(),
+            log: ConfigLog::default(),
+            processadores: processor::registro_padrao(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigServidor {
+    pub porta: u16,
+    // Só `/metrics`, fora dos limites de concorrência de `/payments`.
+    pub porta_admin: u16,
+    // Identifica a instância em registros compartilhados; `HOSTNAME` no contêiner.
+    pub instancia: String,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "ocultar_segredo"
+    )]
+    pub admin_token: Option<String>,
+    // Consultas, sumário e admin.
+    pub prioritario: LimitesRota,
+    // `POST /payments` e `POST /payments/batch`.
+    pub pagamentos: LimitesRota,
+}
+
+impl Default for ConfigServidor {
+    fn default() -> Self {
+        Self {
+            porta: constantes::PORTA,
+            porta_admin: constantes::ADMIN_PORT,
+            instancia: "api".to_string(),
+            admin_token: None,
+            prioritario: LimitesRota {
+                buffer: constantes::BUFFER_PRIORITARIO,
+                concorrencia: constantes::CONCORRENCIA_PRIORITARIO,
+            },
+            pagamentos: LimitesRota {
+                buffer: constantes::BUFFER_PAGAMENTOS,
+                concorrencia: constantes::CONCORRENCIA_PAGAMENTOS,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitesRota {
+    // Requisições esperando vaga antes de o tower responder erro.
+    pub buffer: usize,
+    pub concorrencia: usize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFila {
+    pub backend: BackendFila,
+    pub workers: usize,
+    // Pagamentos em espera por canal de worker (fila em memória).
+    pub capacidade_canal: usize,
+    // Pagamentos processados direto no handler, sem passar pelos canais.
+    pub permissoes_diretas: u32,
+    // Entradas do stream pendentes há mais que isso são reivindicadas por outro worker.
+    pub ocioso_ms: u64,
+}
+
+impl Default for ConfigFila {
+    fn default() -> Self {
+        Self {
+            backend: BackendFila::Memoria,
+            workers: constantes::NUM_CONSUMER,
+            capacidade_canal: constantes::CAPACIDADE_CANAL,
+            permissoes_diretas: constantes::PERMISSOES_DIRETAS,
+            ocioso_ms: constantes::FILA_OCIOSO_MS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigRedis {
+    pub url: String,
+    pub conexoes_por_worker: usize,
+}
+
+impl Default for ConfigRedis {
+    fn default() -> Self {
+        Self {
+            url: constantes::REDIS_URL.to_string(),
+            conexoes_por_worker: constantes::CONEXOES_REDIS_POR_WORKER,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigNats {
+    pub url: String,
+}
+
+impl Default for ConfigNats {
+    fn default() -> Self {
+        Self {
+            url: constantes::NATS_URL.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigPagamentos {
+    // Tentativas por pagamento antes de ir para as dead letters.
+    pub tentativas_maximas: u8,
+    // Fração das tentativas (em %) reservada aos processadores mais prioritários.
+    pub percentual_fallback: f32,
+    pub dedup_janela_secs: u64,
+    pub status_ttl_secs: u64,
+    pub carencia_consulta_ms: u64,
+    // Limite de cada requisição a um processador.
+    #[serde(rename = "timeout_ms", with = "duracao_ms")]
+    pub timeout: Duration,
+    #[serde(rename = "timeout_conexao_ms", with = "duracao_ms")]
+    pub timeout_conexao: Duration,
+}
+
+impl Default for ConfigPagamentos {
+    fn default() -> Self {
+        Self {
+            tentativas_maximas: constantes::TENTATIVAS_PAGAMENTO,
+            percentual_fallback: constantes::PERCENTUAL_FALLBACK,
+            dedup_janela_secs: constantes::DEDUP_JANELA_SECS,
+            status_ttl_secs: constantes::STATUS_TTL_SECS,
+            carencia_consulta_ms: constantes::CARENCIA_CONSULTA_MS,
+            timeout: Duration::from_millis(constantes::TIMEOUT_PROCESSADOR_MS),
+            timeout_conexao: Duration::from_millis(constantes::TIMEOUT_CONEXAO_MS),
+        }
+    }
+}
+
+impl ConfigPagamentos {
+    // Pior caso de um pagamento: cada tentativa (envio ou consulta) esgotando o timeout,
+    // seguida da maior espera entre tentativas. Pausas manuais não contam.
+    pub fn duracao_maxima(&self) -> Duration {
+        (self.timeout + Duration::from_millis(constantes::ESPERA_MAX_TENTATIVA_MS))
+            * self.tentativas_maximas as u32
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigRoteamento {
+    pub estrategia: String,
+    pub penalidade_latencia: f64,
+}
+
+impl Default for ConfigRoteamento {
+    fn default() -> Self {
+        Self {
+            estrategia: constantes::ESTRATEGIA_ROTEAMENTO.to_string(),
+            penalidade_latencia: constantes::PENALIDADE_LATENCIA,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigEncerramento {
+    #[serde(rename = "prazo_ms", with = "duracao_ms")]
+    pub prazo: Duration,
+}
+
+impl Default for ConfigEncerramento {
+    fn default() -> Self {
+        Self {
+            prazo: Duration::from_millis(constantes::ENCERRAMENTO_PRAZO_MS),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigLote {
+    pub max_itens: usize,
+    // Itens de um mesmo lote admitidos ao mesmo tempo.
+    pub concorrencia: usize,
+}
+
+impl Default for ConfigLote {
+    fn default() -> Self {
+        Self {
+            max_itens: constantes::LOTE_MAX_ITENS,
+            concorrencia: constantes::LOTE_CONCORRENCIA,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigAdmissao {
+    pub habilitada: bool,
+    // Tempo estimado para esvaziar a fila acima do qual `POST /payments` responde 429.
+    #[serde(rename = "drenagem_max_ms", with = "duracao_ms")]
+    pub drenagem_max: Duration,
+    // Abaixo disso a fila absorve uma queda dos processadores sem recusar nada.
+    pub fila_minima: usize,
+    pub retry_after_max_secs: u64,
+}
+
+impl Default for ConfigAdmissao {
+    fn default() -> Self {
+        Self {
+            habilitada: true,
+            drenagem_max: Duration::from_millis(constantes::ADMISSAO_DRENAGEM_MAX_MS),
+            fila_minima: constantes::ADMISSAO_FILA_MINIMA,
+            retry_after_max_secs: constantes::ADMISSAO_RETRY_AFTER_MAX_SECS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FormatoLog {
+    Texto,
+    // Uma linha JSON por evento, com os campos dos spans abertos.
+    Json,
+}
+
+impl FromStr for FormatoLog {
+    type Err = String;
+
+    fn from_str(valor: &str) -> Result<Self, Self::Err> {
+        match valor.to_ascii_lowercase().as_str() {
+            "texto" => Ok(FormatoLog::Texto),
+            "json" => Ok(FormatoLog::Json),
+            outro => Err(format!("formato desconhecido: {}", outro)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigLog {
+    // Sintaxe do `EnvFilter` (ex.: "info,rust_backend=debug").
+    pub filtro: String,
+    pub formato: FormatoLog,
+}
+
+impl Default for ConfigLog {
+    fn default() -> Self {
+        Self {
+            filtro: constantes::LOG_FILTRO.to_string(),
+            formato: FormatoLog::Texto,
+        }
+    }
+}
+
+// Durações ficam no arquivo como milissegundos inteiros.
+pub mod duracao_ms {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duracao: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(duracao.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        u64::deserialize(d).map(Duration::from_millis)
+    }
+}
+
+// O `--print-config` não deve vazar o token de admin para logs.
+fn ocultar_segredo<S: Serializer>(valor: &Option<String>, s: S) -> Result<S::Ok, S::Error> {
+    match valor {
+        Some(_) => s.serialize_str("<definido>"),
+        None => s.serialize_none(),
+    }
+}
+
+// Lê variáveis de ambiente por cima da configuração, acumulando os valores inválidos.
+struct Ambiente<'a> {
+    ler: &'a dyn Fn(&str) -> Option<String>,
+    erros: Vec<String>,
+}
+
+impl Ambiente<'_> {
+    fn valor<T>(&mut self, nome: &str, campo: &mut T)
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let Some(bruto) = (self.ler)(nome) else {
+            return;
+        };
+        match bruto.trim().parse() {
+            Ok(valor) => *campo = valor,
+            Err(e) => self.erros.push(format!("{}={:?}: {}", nome, bruto, e)),
+        }
+    }
+
+    fn ms(&mut self, nome: &str, campo: &mut Duration) {
+        let mut ms = campo.as_millis() as u64;
+        self.valor(nome, &mut ms);
+        *campo = Duration::from_millis(ms);
+    }
+
+    // Vazia conta como ausente.
+    fn opcional(&mut self, nome: &str, campo: &mut Option<String>) {
+        if let Some(valor) = (self.ler)(nome) {
+            *campo = Some(valor).filter(|v| !v.is_empty());
+        }
+    }
+}
+
+impl Configuracao {
+    pub fn from_toml(texto: &str) -> Result<Self, String> {
+        toml::from_str(texto).map_err(|e| e.to_string())
+    }
+
+    // Arquivo: `--config`, senão `CONFIG_ARQUIVO`, senão `config.toml` se existir.
+    // Sem arquivo, parte dos padrões.
+    pub fn carregar(arquivo: Option<&Path>) -> Result<Self, String> {
+        let arquivo = arquivo
+            .map(Path::to_path_buf)
+            .or_else(|| env::var_os("CONFIG_ARQUIVO").map(PathBuf::from));
+        let mut config = match arquivo {
+            Some(caminho) => Self::ler_arquivo(&caminho)?,
+            None if Path::new(constantes::CONFIG_ARQUIVO).exists() => {
+                Self::ler_arquivo(Path::new(constantes::CONFIG_ARQUIVO))?
+            }
+            None => Self::default(),
+        };
+
+        let mut erros = config.aplicar_ambiente(&|nome| env::var(nome).ok());
+        erros.extend(config.validar());
+        if !erros.is_empty() {
+            return Err(erros.join("\n"));
+        }
+        Ok(config)
+    }
+
+    fn ler_arquivo(caminho: &Path) -> Result<Self, String> {
+        let texto =
+            fs::read_to_string(caminho).map_err(|e| format!("{}: {}", caminho.display(), e))?;
+        Self::from_toml(&texto).map_err(|e| format!("{}: {}", caminho.display(), e))
+    }
+
+    pub fn para_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("configuração sempre serializável")
+    }
+
+    // Sobrepõe um fragmento (mesmo formato do arquivo) à configuração atual.
+    pub fn mesclar(&self, fragmento: toml::Table) -> Result<Self, String> {
+        let mut base = toml::Table::try_from(self).map_err(|e| e.to_string())?;
+        mesclar_tabelas(&mut base, fragmento);
+        base.try_into().map_err(|e: toml::de::Error| e.to_string())
+    }
+
+    pub fn recarregavel(&self) -> ConfigRecarregavel {
+        ConfigRecarregavel {
+            pagamentos: self.pagamentos,
+            roteamento: self.roteamento.clone(),
+            concorrencia_prioritario: self.servidor.prioritario.concorrencia,
+            concorrencia_pagamentos: self.servidor.pagamentos.concorrencia,
+            processadores: self.processadores.clone(),
+        }
+    }
+
+    pub fn aplicar_recarregavel(&mut self, recarregavel: &ConfigRecarregavel) {
+        // O timeout de conexão é do cliente HTTP, montado uma vez no boot.
+        let timeout_conexao = self.pagamentos.timeout_conexao;
+        self.pagamentos = recarregavel.pagamentos;
+        self.pagamentos.timeout_conexao = timeout_conexao;
+        self.roteamento = recarregavel.roteamento.clone();
+        self.servidor.prioritario.concorrencia = recarregavel.concorrencia_prioritario;
+        self.servidor.pagamentos.concorrencia = recarregavel.concorrencia_pagamentos;
+        self.processadores = recarregavel.processadores.clone();
+    }
+
+    // Seções de `nova` que diferem da atual fora do que a recarga aplica; só valem
+    // depois de reiniciar.
+    pub fn pendentes_de_reinicio(&self, nova: &Configuracao) -> Vec<String> {
+        let mut aplicada = self.clone();
+        aplicada.aplicar_recarregavel(&nova.recarregavel());
+        let (Ok(atual), Ok(nova)) = (
+            toml::Table::try_from(&aplicada),
+            toml::Table::try_from(nova),
+        ) else {
+            return Vec::new();
+        };
+        nova.iter()
+            .filter(|(secao, valor)| atual.get(*secao) != Some(*valor))
+            .map(|(secao, _)| secao.clone())
+            .collect()
+    }
+
+    // Retorna os valores que não puderam ser lidos; os válidos já foram aplicados.
+    pub fn aplicar_ambiente(&mut self, ler: &dyn Fn(&str) -> Option<String>) -> Vec<String> {
+        let mut amb = Ambiente {
+            ler,
+            erros: Vec::new(),
+        };
+
+        let servidor = &mut self.servidor;
+        amb.valor("PORTA", &mut servidor.porta);
+        amb.valor("ADMIN_PORT", &mut servidor.porta_admin);
+        amb.valor("HOSTNAME", &mut servidor.instancia);
+        amb.opcional("ADMIN_TOKEN", &mut servidor.admin_token);
+
+        let fila = &mut self.fila;
+        if let Some(valor) = (amb.ler)("FILA_DURAVEL") {
+            fila.backend = BackendFila::from_env(&valor);
+        }
+        amb.valor("NUM_CONSUMER", &mut fila.workers);
+        amb.valor("CAPACIDADE_CANAL", &mut fila.capacidade_canal);
+        amb.valor("PERMISSOES_DIRETAS", &mut fila.permissoes_diretas);
+        amb.valor("FILA_OCIOSO_MS", &mut fila.ocioso_ms);
+
+        amb.valor("DB_URL", &mut self.redis.url);
+        amb.valor("NATS_URL", &mut self.nats.url);
+
+        let pagamentos = &mut self.pagamentos;
+        amb.valor("TENTATIVAS_PAGAMENTO", &mut pagamentos.tentativas_maximas);
+        amb.valor(
+            "RETRY_DEFAULT_PERCENTAGE",
+            &mut pagamentos.percentual_fallback,
+        );
+        amb.valor("DEDUP_JANELA_SECS", &mut pagamentos.dedup_janela_secs);
+        amb.valor("STATUS_TTL_SECS", &mut pagamentos.status_ttl_secs);
+        amb.valor("CARENCIA_CONSULTA_MS", &mut pagamentos.carencia_consulta_ms);
+        amb.ms("TIMEOUT_PROCESSADOR_MS", &mut pagamentos.timeout);
+
+        amb.valor("ESTRATEGIA_ROTEAMENTO", &mut self.roteamento.estrategia);
+        amb.valor(
+            "PENALIDADE_LATENCIA",
+            &mut self.roteamento.penalidade_latencia,
+        );
+
+        let circuito = &mut self.circuito;
+        amb.valor("CB_FALHAS_CONSECUTIVAS", &mut circuito.falhas_consecutivas);
+        amb.valor("CB_TAXA_ERRO", &mut circuito.taxa_erro_maxima);
+        amb.valor("CB_JANELA", &mut circuito.tamanho_janela);
+        amb.valor("CB_MIN_AMOSTRAS", &mut circuito.minimo_amostras);
+        amb.ms("CB_TEMPO_ABERTO_MS", &mut circuito.tempo_aberto);
+        amb.valor(
+            "CB_TENTATIVAS_MEIO_ABERTO",
+            &mut circuito.tentativas_meio_aberto,
+        );
+
+        amb.ms("SAUDE_INTERVALO_MS", &mut self.saude.intervalo);
+        amb.ms("SAUDE_VALIDADE_MS", &mut self.saude.validade);
+        amb.valor("SAUDE_OBSOLETA", &mut self.saude.politica);
+
+        amb.ms("LIDER_CONCESSAO_MS", &mut self.lideranca.concessao);
+        amb.ms("LIDER_RENOVACAO_MS", &mut self.lideranca.renovacao);
+
+        let estatisticas = &mut self.estatisticas;
+        amb.valor("ESTATISTICAS_JANELA_SECS", &mut estatisticas.janela_secs);
+        amb.ms("ESTATISTICAS_INTERVALO_MS", &mut estatisticas.intervalo);
+        amb.valor("ESTATISTICAS_MIN_AMOSTRAS", &mut estatisticas.min_amostras);
+
+        let armazenamento = &mut self.armazenamento;
+        amb.valor("ARMAZENAMENTO", &mut armazenamento.backend);
+        amb.opcional("ARMAZENAMENTO_URL", &mut armazenamento.url);
+        amb.valor("RETENCAO_SECS", &mut armazenamento.retencao_secs);
+        amb.valor(
+            "COMPACTACAO_INTERVALO_SECS",
+            &mut armazenamento.compactacao_intervalo_secs,
+        );
+
+        amb.ms("ENCERRAMENTO_PRAZO_MS", &mut self.encerramento.prazo);
+        amb.valor("LOTE_MAX_ITENS", &mut self.lote.max_itens);
+        amb.valor("LOTE_CONCORRENCIA", &mut self.lote.concorrencia);
+        let admissao = &mut self.admissao;
+        amb.valor("ADMISSAO_HABILITADA", &mut admissao.habilitada);
+        amb.ms("ADMISSAO_DRENAGEM_MAX_MS", &mut admissao.drenagem_max);
+        amb.valor("ADMISSAO_FILA_MINIMA", &mut admissao.fila_minima);
+        amb.valor(
+            "ADMISSAO_RETRY_AFTER_MAX_SECS",
+            &mut admissao.retry_after_max_secs,
+        );
+        amb.valor("RUST_LOG", &mut self.log.filtro);
+        amb.valor("LOG_FORMATO", &mut self.log.formato);
+
+        // `PROCESSADORES` (array JSON) troca a lista inteira; `URL_DEFAULT`/`URL_FALLBACK`
+        // só apontam os processadores com esses nomes para outro endereço.
+        if let Some(json) = (amb.ler)("PROCESSADORES") {
+            match serde_json::from_str(&json) {
+                Ok(registro) => self.processadores = registro,
+                Err(e) => amb.erros.push(format!("PROCESSADORES inválido: {}", e)),
+            }
+        }
+        for (variavel, nome) in [("URL_DEFAULT", "default"), ("URL_FALLBACK", "fallback")] {
+            if let Some(url) = (amb.ler)(variavel)
+                && let Some(config) = self.processadores.iter_mut().find(|p| p.nome == nome)
+            {
+                config.url = url;
+            }
+        }
+
+        amb.erros
+    }
+
+    pub fn validar(&self) -> Vec<String> {
+        let mut erros = Vec::new();
+        let mut exigir = |condicao: bool, erro: &str| {
+            if !condicao {
+                erros.push(erro.to_string());
+            }
+        };
+
+        let servidor = &self.servidor;
+        exigir(
+            servidor.porta != servidor.porta_admin,
+            "servidor.porta e servidor.porta_admin precisam ser diferentes",
+        );
+        for limites in [servidor.prioritario, servidor.pagamentos] {
+            exigir(
+                limites.buffer > 0 && limites.concorrencia > 0,
+                "servidor: buffer e concorrencia das rotas devem ser maiores que zero",
+            );
+        }
+        exigir(
+            self.fila.workers > 0,
+            "fila.workers deve ser maior que zero",
+        );
+        exigir(
+            self.fila.capacidade_canal > 0,
+            "fila.capacidade_canal deve ser maior que zero",
+        );
+        exigir(
+            self.fila.permissoes_diretas > 0,
+            "fila.permissoes_diretas deve ser maior que zero",
+        );
+        exigir(!self.redis.url.is_empty(), "redis.url não pode ser vazia");
+        exigir(
+            self.fila.ocioso_ms > 0,
+            "fila.ocioso_ms deve ser maior que zero",
+        );
+        // Antes disso outro consumidor reivindicaria uma entrada ainda em andamento.
+        exigir(
+            self.fila.backend == BackendFila::Memoria
+                || Duration::from_millis(self.fila.ocioso_ms) > self.pagamentos.duracao_maxima(),
+            "fila.ocioso_ms deve passar do pior caso de um pagamento \
+             (pagamentos.tentativas_maximas × (timeout_ms + 1s))",
+        );
+        exigir(
+            self.redis.conexoes_por_worker > 0,
+            "redis.conexoes_por_worker deve ser maior que zero",
+        );
+        exigir(!self.nats.url.is_empty(), "nats.url não pode ser vazia");
+        exigir(
+            self.pagamentos.tentativas_maximas > 0,
+            "pagamentos.tentativas_maximas deve ser maior que zero",
+        );
+        exigir(
+            (0.0..=100.0).contains(&self.pagamentos.percentual_fallback),
+            "pagamentos.percentual_fallback deve ficar entre 0 e 100",
+        );
+        exigir(
+            !self.pagamentos.timeout.is_zero() && !self.pagamentos.timeout_conexao.is_zero(),
+            "pagamentos.timeout_ms e timeout_conexao_ms devem ser maiores que zero",
+        );
+        exigir(
+            self.roteamento.penalidade_latencia >= 0.0,
+            "roteamento.penalidade_latencia não pode ser negativa",
+        );
+        exigir(
+            !self.saude.intervalo.is_zero(),
+            "saude.intervalo_ms deve ser maior que zero",
+        );
+        // Com menos, o seguidor daria por obsoleta uma saúde que a líder nem teve tempo de
+        // renovar.
+        exigir(
+            !self.saude.validade.is_zero() && self.saude.validade >= self.saude.intervalo,
+            "saude.validade_ms deve ser pelo menos saude.intervalo_ms",
+        );
+        exigir(
+            self.lote.max_itens > 0 && self.lote.concorrencia > 0,
+            "lote.max_itens e lote.concorrencia devem ser maiores que zero",
+        );
+        exigir(
+            !self.admissao.drenagem_max.is_zero() && self.admissao.retry_after_max_secs > 0,
+            "admissao.drenagem_max_ms e retry_after_max_secs devem ser maiores que zero",
+        );
+
+        let validacoes = [
+            roteamento::criar_estrategia(
+                &self.roteamento.estrategia,
+                self.roteamento.penalidade_latencia,
+            )
+            .map(|_| ())
+            .map_err(|e| format!("roteamento.estrategia: {}", e)),
+            self.circuito.validar(),
+            self.lideranca.validar(),
+            self.estatisticas.validar(),
+            self.armazenamento.validar(),
+            processor::validar_registro(&self.processadores)
+                .map_err(|e| format!("processadores: {}", e)),
+            EnvFilter::try_new(&self.log.filtro)
+                .map(|_| ())
+                .map_err(|e| format!("log.filtro: {}", e)),
+        ];
+        erros.extend(validacoes.into_iter().filter_map(Result::err));
+        erros
+    }
+}
+
+fn mesclar_tabelas(base: &mut toml::Table, fragmento: toml::Table) {
+    for (chave, valor) in fragmento {
+        match (base.get_mut(&chave), valor) {
+            (Some(toml::Value::Table(atual)), toml::Value::Table(novo)) => {
+                mesclar_tabelas(atual, novo)
+            }
+            (_, valor) => {
+                base.insert(chave, valor);
+            }
+        }
+    }
+}
+
+// O que muda sem reiniciar: o que `processa_pagamento` e `escolher_processador` leem a
+// cada pagamento, os processadores existentes e os limites de concorrência das rotas.
+// É também o que vai no NATS para a outra instância.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigRecarregavel {
+    pub pagamentos: ConfigPagamentos,
+    pub roteamento: ConfigRoteamento,
+    pub concorrencia_prioritario: usize,
+    pub concorrencia_pagamentos: usize,
+    pub processadores: Vec<ConfigProcessador>,
+}
+
+pub struct Argumentos {
+    pub arquivo: Option<PathBuf>,
+    // `--print-config`: mostra a configuração efetiva (arquivo + ambiente) e sai.
+    pub imprimir: bool,
+}
+
+impl Argumentos {
+    pub fn ler(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut argumentos = Argumentos {
+            arquivo: None,
+            imprimir: false,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--print-config" => argumentos.imprimir = true,
+                "--config" => {
+                    let caminho = args.next().ok_or("--config exige um caminho")?;
+                    argumentos.arquivo = Some(PathBuf::from(caminho));
+                }
+                outro => match outro.strip_prefix("--config=") {
+                    Some(caminho) => argumentos.arquivo = Some(PathBuf::from(caminho)),
+                    None => {
+                        return Err(format!(
+                            "argumento desconhecido: {} (uso: rust-backend [--config arquivo.toml] [--print-config])",
+                            outro
+                        ));
+                    }
+                },
+            }
+        }
+        Ok(argumentos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::models::processor::PoliticaSaudeObsoleta;
+
+    fn ambiente(pares: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let mapa: HashMap<String, String> = pares
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |nome| mapa.get(nome).cloned()
+    }
+
+    #[test]
+    fn padroes_sao_validos_e_voltam_do_toml() {
+        let config = Configuracao::default();
+        assert!(config.validar().is_empty());
+
+        let relida = Configuracao::from_toml(&config.para_toml()).unwrap();
+        assert_eq!(relida.para_toml(), config.para_toml());
+        assert_eq!(relida.processadores.len(), 2);
+    }
+
+    #[test]
+    fn arquivo_parcial_completa_com_padroes() {
+        let config = Configuracao::from_toml(
+            r#"
+            [fila]
+            workers = 8
+            backend = "redis_streams"
+
+            [saude]
+            obsoleta = "degradar"
+            validade_ms = 2000
+
+            [[processadores]]
+            nome = "unico"
+            url = "http://pp:8080"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.fila.workers, 8);
+        assert_eq!(config.fila.backend, BackendFila::RedisStreams);
+        assert_eq!(config.fila.capacidade_canal, constantes::CAPACIDADE_CANAL);
+        assert_eq!(config.saude.politica, PoliticaSaudeObsoleta::Degradar);
+        assert_eq!(config.saude.validade, Duration::from_millis(2000));
+        assert_eq!(config.processadores.len(), 1);
+        assert_eq!(config.processadores[0].peso, 1);
+
+        let erro = Configuracao::from_toml("[fila]\nworker = 8\n").unwrap_err();
+        assert!(erro.contains("worker"), "{}", erro);
+    }
+
+    #[test]
+    fn ambiente_sobrescreve_e_acusa_valores_invalidos() {
+        let mut config = Configuracao::default();
+        let erros = config.aplicar_ambiente(&ambiente(&[
+            ("NUM_CONSUMER", "200"),
+            ("RETRY_DEFAULT_PERCENTAGE", "60"),
+            ("URL_FALLBACK", "http://pp-fallback:8080"),
+            ("ADMIN_TOKEN", ""),
+            ("CB_JANELA", "vinte"),
+            ("SAUDE_OBSOLETA", "ignorar"),
+        ]));
+        assert_eq!(config.fila.workers, 200);
+        assert_eq!(config.pagamentos.percentual_fallback, 60.0);
+        assert_eq!(config.processadores[1].url, "http://pp-fallback:8080");
+        assert_eq!(config.servidor.admin_token, None);
+        assert_eq!(config.circuito.tamanho_janela, constantes::CB_JANELA);
+        assert_eq!(erros.len(), 2);
+        assert!(erros[0].starts_with("CB_JANELA="), "{:?}", erros);
+    }
+
+    #[test]
+    fn validacao_lista_todos_os_problemas() {
+        let mut config = Configuracao::default();
+        config.fila.workers = 0;
+        config.roteamento.estrategia = "aleatoria".to_string();
+        config.lideranca.renovacao = config.lideranca.concessao;
+        config.processadores.clear();
+        assert_eq!(config.validar().len(), 4);
+    }
+
+    #[test]
+    fn ocioso_e_validade_da_saude_nao_podem_ser_zero() {
+        let mut config = Configuracao::default();
+        config.fila.ocioso_ms = 0;
+        config.saude.validade = Duration::ZERO;
+        let erros = config.validar();
+        assert_eq!(erros.len(), 2, "{:?}", erros);
+        assert!(erros[0].starts_with("fila.ocioso_ms"), "{:?}", erros);
+        assert!(erros[1].starts_with("saude.validade_ms"), "{:?}", erros);
+
+        // Na fila durável, zero também fica abaixo do pior caso de um pagamento.
+        config.fila.backend = BackendFila::RedisStreams;
+        config.saude.validade = config.saude.intervalo;
+        assert_eq!(config.validar().len(), 2);
+    }
+
+    #[test]
+    fn validade_da_saude_nao_fica_abaixo_do_intervalo() {
+        let mut config = Configuracao::default();
+        config.saude.validade = config.saude.intervalo - Duration::from_millis(1);
+        assert_eq!(config.validar().len(), 1);
+
+        let erros = config.aplicar_ambiente(&ambiente(&[("SAUDE_VALIDADE_MS", "0")]));
+        assert!(erros.is_empty(), "{:?}", erros);
+        assert_eq!(config.validar().len(), 1);
+    }
+
+    #[test]
+    fn fila_duravel_exige_ocioso_acima_do_pior_caso() {
+        let mut config = Configuracao::default();
+        config.fila.backend = BackendFila::RedisStreams;
+        assert!(config.validar().is_empty());
+
+        // 40 tentativas × (5s + 1s) = 240s.
+        config.fila.ocioso_ms = 240_000;
+        let erros = config.validar();
+        assert_eq!(erros.len(), 1);
+        assert!(erros[0].starts_with("fila.ocioso_ms"), "{:?}", erros);
+
+        // Recarga que aumenta as tentativas também é recusada.
+        config.fila.ocioso_ms = constantes::FILA_OCIOSO_MS;
+        config.pagamentos.tentativas_maximas = 60;
+        assert_eq!(config.validar().len(), 1);
+
+        config.fila.backend = BackendFila::Memoria;
+        assert!(config.validar().is_empty());
+    }
+
+    #[test]
+    fn fragmento_mesclado_separa_o_que_exige_reinicio() {
+        let atual = Configuracao::default();
+        let fragmento: toml::Table = toml::from_str(
+            r#"
+            pagamentos = { percentual_fallback = 50.0 }
+            servidor = { pagamentos = { concorrencia = 100 } }
+            fila = { workers = 4 }
+            "#,
+        )
+        .unwrap();
+        let nova = atual.mesclar(fragmento).unwrap();
+        assert_eq!(nova.pagamentos.percentual_fallback, 50.0);
+        assert_eq!(
+            nova.pagamentos.tentativas_maximas,
+            atual.pagamentos.tentativas_maximas
+        );
+        assert_eq!(nova.servidor.pagamentos.concorrencia, 100);
+        assert_eq!(
+            nova.servidor.pagamentos.buffer,
+            atual.servidor.pagamentos.buffer
+        );
+        assert_eq!(atual.pendentes_de_reinicio(&nova), vec!["fila".to_string()]);
+
+        let mut aplicada = atual.clone();
+        aplicada.aplicar_recarregavel(&nova.recarregavel());
+        assert_eq!(aplicada.pagamentos.percentual_fallback, 50.0);
+        assert_eq!(aplicada.fila.workers, atual.fila.workers);
+    }
+
+    #[test]
+    fn argumentos_de_linha_de_comando() {
+        let ler = |args: &[&str]| Argumentos::ler(args.iter().map(|a| a.to_string()));
+        let argumentos = ler(&["--config", "a.toml", "--print-config"]).unwrap();
+        assert_eq!(argumentos.arquivo, Some(PathBuf::from("a.toml")));
+        assert!(argumentos.imprimir);
+        assert!(ler(&["--config=b.toml"]).unwrap().arquivo.is_some());
+        assert!(ler(&["--config"]).is_err());
+        assert!(ler(&["--verbose"]).is_err());
+    }
+}
@@ -2,4 +2,70 @@ pub const URL_DEFAULT: &str = "http://localhost:8001";
 pub const URL_FALLBACK: &str = "http://localhost:8002";
 pub const REDIS_URL: &str = "redis://localhost:6379/";
 pub const NATS_URL: &str = "nats://localhost:4222";
-pub const NUM_CONSUMER: u8 = 20;
+pub const NUM_CONSUMER: usize = 20;
+pub const ESTRATEGIA_ROTEAMENTO: &str = "prioridade";
+pub const PENALIDADE_LATENCIA: f64 = 0.0001;
+pub const FILA_STREAM: &str = "payments_stream";
+pub const FILA_GRUPO: &str = "payments_workers";
+pub const FILA_CAMPO: &str = "body";
+pub const FILA_OCIOSO_MS: u64 = 300_000;
+pub const DEAD_LETTERS: &str = "dead_letters";
+pub const DEAD_LETTERS_POR_DATA: &str = "dead_letters_by_date";
+pub const DEDUP_JANELA_SECS: u64 = 300;
+pub const STATUS_TTL_SECS: u64 = 3600;
+pub const CB_FALHAS_CONSECUTIVAS: u32 = 5;
+pub const CB_TAXA_ERRO: f64 = 0.5;
+pub const CB_JANELA: usize = 20;
+pub const CB_MIN_AMOSTRAS: usize = 10;
+pub const CB_TEMPO_ABERTO_MS: u64 = 1000;
+pub const CB_TENTATIVAS_MEIO_ABERTO: u32 = 3;
+pub const CARENCIA_CONSULTA_MS: u64 = 2000;
+pub const PAGAMENTOS_POR_DATA: &str = "payments_by_date";
+pub const BALDES_POR_SEGUNDO: &str = "summary_buckets";
+pub const PREFIXO_BALDE: &str = "summary:";
+pub const ARMAZENAMENTO: &str = "redis";
+pub const RETENCAO_SECS: u64 = 86_400;
+pub const COMPACTACAO_INTERVALO_SECS: u64 = 60;
+pub const AUDITORIA_EXPURGOS: &str = "purge_audit";
+pub const AUDITORIA_EXPURGOS_MAX: isize = 1000;
+pub const PORTA: u16 = 9999;
+pub const ADMIN_PORT: u16 = 9100;
+pub const LOG_FILTRO: &str = "info";
+pub const LIDER_CONCESSAO: &str = "leader:lease";
+pub const LIDER_FENCING: &str = "leader:fencing_token";
+pub const LIDER_CONCESSAO_MS: u64 = 5000;
+pub const LIDER_RENOVACAO_MS: u64 = 1500;
+pub const SAUDE_SNAPSHOT: &str = "health:";
+pub const SAUDE_VALIDADE_MS: u64 = 15_000;
+pub const ESTATISTICAS_JANELA_SECS: u64 = 10;
+pub const ESTATISTICAS_INTERVALO_MS: u64 = 1000;
+pub const ESTATISTICAS_MIN_AMOSTRAS: u64 = 20;
+pub const ESTATISTICAS_SUBJECT: &str = "processor.stats";
+pub const ENCERRAMENTO_PRAZO_MS: u64 = 5000;
+pub const SOBRAS_ENCERRAMENTO: &str = "payments_spill";
+pub const PERMISSOES_DIRETAS: u32 = 100;
+pub const LOTE_MAX_ITENS: usize = 1000;
+pub const LOTE_CONCORRENCIA: usize = 16;
+pub const CONFIG_ARQUIVO: &str = "config.toml";
+pub const RECARGA_SUBJECT: &str = "config.reload";
+pub const CAPACIDADE_CANAL: usize = 300;
+pub const CONEXOES_REDIS_POR_WORKER: usize = 3;
+pub const TENTATIVAS_PAGAMENTO: u8 = 40;
+pub const ESPERA_MAX_TENTATIVA_MS: u64 = 1000;
+pub const PERCENTUAL_FALLBACK: f32 = 75.0;
+pub const TIMEOUT_PROCESSADOR_MS: u64 = 5000;
+pub const TIMEOUT_CONEXAO_MS: u64 = 2000;
+pub const SAUDE_INTERVALO_MS: u64 = 5000;
+pub const BUFFER_PRIORITARIO: usize = 16;
+pub const CONCORRENCIA_PRIORITARIO: usize = 16;
+pub const BUFFER_PAGAMENTOS: usize = 6 * 1024;
+pub const CONCORRENCIA_PAGAMENTOS: usize = 800;
+pub const INTERVENCAO: &str = "override:";
+pub const INTERVENCAO_SUBJECT: &str = "admin.override";
+pub const INTERVENCAO_MAX_SECS: u64 = 86_400;
+pub const PRONTIDAO_TIMEOUT_MS: u64 = 1000;
+pub const PRONTIDAO_OCUPACAO_MAXIMA: f64 = 0.9;
+pub const ADMISSAO_DRENAGEM_MAX_MS: u64 = 20_000;
+pub const ADMISSAO_FILA_MINIMA: usize = 1000;
+pub const ADMISSAO_RETRY_AFTER_MAX_SECS: u64 = 30;
+pub const ADMISSAO_MEDICAO_MS: u64 = 100;
@@ -0,0 +1,92 @@
+use std::time::{Duration, Instant};
+
+use tokio::sync::watch;
+
+// Sinal compartilhado de encerramento. Depois de `iniciar`, os workers param de pegar
+// trabalho novo e terminam o que está nas filas; passado o prazo, o que sobrou vai para
+// o Redis em vez de ser processado.
+pub struct Encerramento {
+    // `Some(limite)` a partir do início do encerramento.
+    limite: watch::Sender<Option<Instant>>,
+    pub prazo: Duration,
+}
+
+impl Encerramento {
+    pub fn new(prazo: Duration) -> Self {
+        Self {
+            limite: watch::Sender::new(None),
+            prazo,
+        }
+    }
+
+    // Idempotente: um segundo sinal não adia o prazo.
+    pub fn iniciar(&self) {
+        let prazo = self.prazo;
+        self.limite.send_if_modified(|limite| {
+            if limite.is_some() {
+                return false;
+            }
+            *limite = Some(Instant::now() + prazo);
+            true
+        });
+    }
+
+    pub fn encerrando(&self) -> bool {
+        self.limite.borrow().is_some()
+    }
+
+    pub fn prazo_esgotado(&self) -> bool {
+        self.limite
+            .borrow()
+            .is_some_and(|limite| Instant::now() >= limite)
+    }
+
+    pub async fn aguardar_inicio(&self) {
+        let mut receptor = self.limite.subscribe();
+        let _ = receptor.wait_for(Option::is_some).await;
+    }
+}
+
+// SIGTERM (docker stop) ou Ctrl+C.
+pub async fn sinal_encerramento() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let sigterm = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sinal) => {
+                sinal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let sigterm = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = sigterm => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn prazo_comeca_no_primeiro_sinal() {
+        let encerramento = Encerramento::new(Duration::from_secs(60));
+        assert!(!encerramento.encerrando());
+        assert!(!encerramento.prazo_esgotado());
+
+        encerramento.iniciar();
+        encerramento.aguardar_inicio().await;
+        assert!(encerramento.encerrando());
+        assert!(!encerramento.prazo_esgotado());
+
+        let sem_prazo = Encerramento::new(Duration::ZERO);
+        sem_prazo.iniciar();
+        assert!(sem_prazo.prazo_esgotado());
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{configuracao::duracao_ms, constantes};
+
+// Limites superiores (ms) das faixas de latência; uma faixa extra conta o que passou de 5s.
+// Faixas fixas permitem somar os resumos de várias instâncias e ainda tirar percentis.
+pub const FAIXAS_LATENCIA_MS: [u64; 10] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigEstatisticas {
+    pub janela_secs: u64,
+    // Intervalo de publicação; o resumo de um par vale por três intervalos.
+    #[serde(rename = "intervalo_ms", with = "duracao_ms")]
+    pub intervalo: Duration,
+    // Abaixo disso o roteamento continua usando só o health check.
+    pub min_amostras: u64,
+}
+
+impl Default for ConfigEstatisticas {
+    fn default() -> Self {
+        Self {
+            janela_secs: constantes::ESTATISTICAS_JANELA_SECS,
+            intervalo: Duration::from_millis(constantes::ESTATISTICAS_INTERVALO_MS),
+            min_amostras: constantes::ESTATISTICAS_MIN_AMOSTRAS,
+        }
+    }
+}
+
+impl ConfigEstatisticas {
+    pub fn validar(&self) -> Result<(), String> {
+        if self.janela_secs == 0 {
+            return Err("estatisticas.janela_secs deve ser maior que zero".to_string());
+        }
+        if self.intervalo < Duration::from_millis(100) {
+            return Err("estatisticas.intervalo_ms deve ser de pelo menos 100".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn validade_pares(&self) -> Duration {
+        self.intervalo * 3
+    }
+}
+
+// Desfechos das requisições reais a um processador. `erros` usa o mesmo critério do
+// disjuntor (5xx, 429 e falhas de rede); `timeouts` é a parte deles que estourou o prazo.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumoResultados {
+    pub total: u64,
+    pub erros: u64,
+    pub timeouts: u64,
+    #[serde(rename = "latenciaMs")]
+    pub latencia: [u64; FAIXAS_LATENCIA_MS.len() + 1],
+}
+
+impl ResumoResultados {
+    pub fn registrar(&mut self, latencia: Duration, erro: bool, timeout: bool) {
+        let ms = latencia.as_millis() as u64;
+        let faixa = FAIXAS_LATENCIA_MS
+            .iter()
+            .position(|limite| ms <= *limite)
+            .unwrap_or(FAIXAS_LATENCIA_MS.len());
+        self.total += 1;
+        self.erros += erro as u64;
+        self.timeouts += timeout as u64;
+        self.latencia[faixa] += 1;
+    }
+
+    pub fn somar(&mut self, outro: &ResumoResultados) {
+        self.total += outro.total;
+        self.erros += outro.erros;
+        self.timeouts += outro.timeouts;
+        for (faixa, quantidade) in self.latencia.iter_mut().zip(outro.latencia) {
+            *faixa += quantidade;
+        }
+    }
+
+    pub fn taxa_erro(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.erros as f64 / self.total as f64
+    }
+
+    // Limite superior da faixa onde cai o percentil `p` (0..=1). Acima da última faixa,
+    // responde o dobro dela.
+    pub fn percentil(&self, p: f64) -> Option<u64> {
+        if self.total == 0 {
+            return None;
+        }
+        let alvo = ((p * self.total as f64).ceil() as u64).clamp(1, self.total);
+        let mut acumulado = 0;
+        for (faixa, quantidade) in self.latencia.iter().enumerate() {
+            acumulado += quantidade;
+            if acumulado >= alvo {
+                return Some(
+                    FAIXAS_LATENCIA_MS
+                        .get(faixa)
+                        .copied()
+                        .unwrap_or(FAIXAS_LATENCIA_MS[FAIXAS_LATENCIA_MS.len() - 1] * 2),
+                );
+            }
+        }
+        None
+    }
+}
+
+// Janela deslizante de `janela_secs` em baldes de um segundo, sem alocar no caminho quente.
+#[derive(Debug)]
+pub struct JanelaResultados {
+    inicio: Instant,
+    // (segundo desde `inicio`, resumo daquele segundo)
+    baldes: Vec<(u64, ResumoResultados)>,
+}
+
+impl JanelaResultados {
+    pub fn new(janela_secs: u64) -> Self {
+        Self {
+            inicio: Instant::now(),
+            baldes: vec![(u64::MAX, ResumoResultados::default()); janela_secs as usize],
+        }
+    }
+
+    fn segundo(&self, agora: Instant) -> u64 {
+        agora.saturating_duration_since(self.inicio).as_secs()
+    }
+
+    pub fn registrar(&mut self, agora: Instant, latencia: Duration, erro: bool, timeout: bool) {
+        let segundo = self.segundo(agora);
+        let total_baldes = self.baldes.len() as u64;
+        let (marca, resumo) = &mut self.baldes[(segundo % total_baldes) as usize];
+        if *marca != segundo {
+            *marca = segundo;
+            *resumo = ResumoResultados::default();
+        }
+        resumo.registrar(latencia, erro, timeout);
+    }
+
+    pub fn resumo(&self, agora: Instant) -> ResumoResultados {
+        let segundo = self.segundo(agora);
+        let total_baldes = self.baldes.len() as u64;
+        let mut total = ResumoResultados::default();
+        for (marca, resumo) in &self.baldes {
+            if *marca <= segundo && segundo - *marca < total_baldes {
+                total.somar(resumo);
+            }
+        }
+        total
+    }
+}
+
+// O que esta instância viu de um processador mais o último resumo publicado por cada par.
+#[derive(Debug)]
+pub struct EstatisticasProcessador {
+    pub local: JanelaResultados,
+    pares: HashMap<String, (Instant, ResumoResultados)>,
+}
+
+impl EstatisticasProcessador {
+    pub fn new(janela_secs: u64) -> Self {
+        Self {
+            local: JanelaResultados::new(janela_secs),
+            pares: HashMap::new(),
+        }
+    }
+
+    pub fn atualizar_par(
+        &mut self,
+        instancia: String,
+        resumo: ResumoResultados,
+        agora: Instant,
+        validade: Duration,
+    ) {
+        // Pares que sumiram saem aqui, sem precisar de uma tarefa de limpeza.
+        self.pares
+            .retain(|_, (recebido, _)| agora.saturating_duration_since(*recebido) <= validade);
+        self.pares.insert(instancia, (agora, resumo));
+    }
+
+    // Visão do cluster: a janela local somada aos resumos de pares ainda válidos.
+    pub fn consolidado(&self, agora: Instant, validade: Duration) -> ResumoResultados {
+        let mut total = self.local.resumo(agora);
+        for (recebido, resumo) in self.pares.values() {
+            if agora.saturating_duration_since(*recebido) <= validade {
+                total.somar(resumo);
+            }
+        }
+        total
+    }
+}
+
+// Publicado por cada instância a cada intervalo, com a janela local de cada processador.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicacaoResultados {
+    pub instancia: String,
+    pub processadores: HashMap<String, ResumoResultados>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn percentis_pelas_faixas() {
+        let mut resumo = ResumoResultados::default();
+        assert_eq!(resumo.percentil(0.5), None);
+
+        for _ in 0..8 {
+            resumo.registrar(ms(20), false, false);
+        }
+        resumo.registrar(ms(400), true, false);
+        resumo.registrar(ms(9000), true, true);
+
+        assert_eq!(resumo.percentil(0.5), Some(25));
+        assert_eq!(resumo.percentil(0.9), Some(500));
+        assert_eq!(resumo.percentil(1.0), Some(10_000));
+        assert_eq!(resumo.taxa_erro(), 0.2);
+        assert_eq!(resumo.timeouts, 1);
+    }
+
+    #[test]
+    fn janela_descarta_segundos_antigos() {
+        let mut janela = JanelaResultados::new(3);
+        let t0 = janela.inicio;
+        janela.registrar(t0, ms(1), false, false);
+        janela.registrar(t0 + Duration::from_secs(1), ms(1), true, false);
+
+        assert_eq!(janela.resumo(t0 + Duration::from_secs(2)).total, 2);
+        assert_eq!(janela.resumo(t0 + Duration::from_secs(3)).total, 1);
+        assert_eq!(janela.resumo(t0 + Duration::from_secs(4)).total, 0);
+
+        // O balde reaproveitado começa do zero.
+        janela.registrar(t0 + Duration::from_secs(3), ms(1), false, false);
+        let resumo = janela.resumo(t0 + Duration::from_secs(3));
+        assert_eq!((resumo.total, resumo.erros), (2, 1));
+    }
+
+    #[test]
+    fn consolidado_soma_pares_validos() {
+        let mut estatisticas = EstatisticasProcessador::new(10);
+        let agora = Instant::now();
+        estatisticas.local.registrar(agora, ms(20), false, false);
+
+        let mut do_par = ResumoResultados::default();
+        do_par.registrar(ms(20), true, false);
+        let validade = Duration::from_secs(3);
+        estatisticas.atualizar_par("api02".to_string(), do_par.clone(), agora, validade);
+        estatisticas.atualizar_par("api02".to_string(), do_par, agora, validade);
+
+        let resumo = estatisticas.consolidado(agora, validade);
+        assert_eq!((resumo.total, resumo.erros), (2, 1));
+
+        let depois = agora + Duration::from_secs(4);
+        assert_eq!(estatisticas.consolidado(depois, validade).erros, 0);
+    }
+}
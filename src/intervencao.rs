@@ -0,0 +1,252 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{api::redis, appstate::AppState, constantes};
+
+// Decisão manual de um operador; vale até `expira_em` e tem precedência sobre a saúde.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Acao {
+    // Todo o tráfego vai para este processador, mesmo com o health check dizendo que
+    // falha; os demais ficam de fora.
+    Forcar,
+    // Não recebe pagamentos novos; os que já foram enviados terminam.
+    Drenar,
+    // Ignora o `failing` do health check. O disjuntor continua valendo.
+    Saudavel,
+    // Global: os workers seguram os pagamentos, sem gastar tentativas, até a pausa acabar.
+    Pausar,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Intervencao {
+    pub acao: Acao,
+    #[serde(rename = "expiraEm")]
+    pub expira_em: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motivo: Option<String>,
+}
+
+// Corpo de `PUT /admin/processadores/{nome}/intervencao` e `PUT /admin/pausa` (sem `acao`).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PedidoIntervencao {
+    #[serde(default)]
+    pub acao: Option<Acao>,
+    #[serde(rename = "duracaoSecs")]
+    pub duracao_secs: u64,
+    #[serde(default)]
+    pub motivo: Option<String>,
+}
+
+impl PedidoIntervencao {
+    pub fn validar(self, pausa: bool, agora: DateTime<Utc>) -> Result<Intervencao, String> {
+        if self.duracao_secs == 0 || self.duracao_secs > constantes::INTERVENCAO_MAX_SECS {
+            return Err(format!(
+                "duracaoSecs deve estar entre 1 e {}",
+                constantes::INTERVENCAO_MAX_SECS
+            ));
+        }
+        let acao = match (pausa, self.acao) {
+            (true, None | Some(Acao::Pausar)) => Acao::Pausar,
+            (false, Some(acao)) if acao != Acao::Pausar => acao,
+            (true, Some(_)) => return Err("a pausa não aceita outra ação".to_string()),
+            (false, _) => {
+                return Err("acao deve ser forcar, drenar ou saudavel".to_string());
+            }
+        };
+        Ok(Intervencao {
+            acao,
+            expira_em: agora + Duration::from_secs(self.duracao_secs),
+            motivo: self.motivo,
+        })
+    }
+}
+
+// Intervenção de um alvo (um processador ou a pausa global).
+#[derive(Debug, Default)]
+pub struct Controle {
+    atual: Option<Intervencao>,
+    // Mudanças (definir ou remover) emitidas antes da última aplicada chegaram atrasadas
+    // e são descartadas.
+    alterado_em: Option<DateTime<Utc>>,
+}
+
+impl Controle {
+    pub fn alterar(&mut self, intervencao: Option<Intervencao>, em: DateTime<Utc>) -> bool {
+        if self.alterado_em.is_some_and(|atual| atual > em) {
+            return false;
+        }
+        self.atual = intervencao;
+        self.alterado_em = Some(em);
+        true
+    }
+
+    pub fn ativa(&self, agora: DateTime<Utc>) -> Option<&Intervencao> {
+        self.atual.as_ref().filter(|i| i.expira_em > agora)
+    }
+}
+
+// Publicada no NATS e guardada no Redis até expirar, para quem sobe no meio do incidente.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MensagemIntervencao {
+    pub origem: String,
+    // `None` = pausa global.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub processador: Option<String>,
+    // `None` = remove a intervenção do alvo.
+    pub intervencao: Option<Intervencao>,
+    #[serde(rename = "emitidaEm")]
+    pub emitida_em: DateTime<Utc>,
+}
+
+pub fn pausado(state: &AppState) -> bool {
+    state.pausa.lock().unwrap().ativa(Utc::now()).is_some()
+}
+
+// `Ok(false)` quando a mensagem é mais antiga que a última mudança do alvo.
+pub async fn aplicar(state: &AppState, mensagem: &MensagemIntervencao) -> Result<bool, String> {
+    let Some(nome) = &mensagem.processador else {
+        return Ok(state
+            .pausa
+            .lock()
+            .unwrap()
+            .alterar(mensagem.intervencao.clone(), mensagem.emitida_em));
+    };
+    for processor in state.processors.iter() {
+        let mut guard = processor.write().await;
+        if &guard.nome == nome {
+            return Ok(guard
+                .intervencao
+                .alterar(mensagem.intervencao.clone(), mensagem.emitida_em));
+        }
+    }
+    Err(format!("processador desconhecido: {}", nome))
+}
+
+// Aplica aqui, guarda no Redis e repassa às outras instâncias.
+pub async fn definir(
+    state: &AppState,
+    processador: Option<String>,
+    intervencao: Option<Intervencao>,
+) -> Result<MensagemIntervencao, String> {
+    let mensagem = MensagemIntervencao {
+        origem: state.lideranca.identidade.clone(),
+        processador,
+        intervencao,
+        emitida_em: Utc::now(),
+    };
+    aplicar(state, &mensagem).await?;
+    info!(
+        processador = mensagem.processador.as_deref().unwrap_or("*"),
+        intervencao = ?mensagem.intervencao,
+        "intervenção manual alterada"
+    );
+
+    if let Err(e) = redis::salvar_intervencao(state, &mensagem).await {
+        warn!(erro = %e, "falha ao guardar a intervenção no Redis");
+    }
+    if let Err(e) = state
+        .nats_client
+        .publish(
+            constantes::INTERVENCAO_SUBJECT,
+            serde_json::to_vec(&mensagem).unwrap().into(),
+        )
+        .await
+    {
+        warn!(erro = %e, "falha ao repassar a intervenção via NATS");
+    }
+    Ok(mensagem)
+}
+
+pub async fn carregar_intervencoes_persistidas(state: &AppState) {
+    let mut alvos = vec![None];
+    for processor in state.processors.iter() {
+        alvos.push(Some(processor.read().await.nome.clone()));
+    }
+    for alvo in alvos {
+        match redis::buscar_intervencao(state, alvo.as_deref()).await {
+            Ok(Some(mensagem)) => {
+                let _ = aplicar(state, &mensagem).await;
+            }
+            Ok(None) => {}
+            Err(e) => warn!(erro = %e, "falha ao carregar intervenção persistida"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intervencao(acao: Acao, expira_em: DateTime<Utc>) -> Option<Intervencao> {
+        Some(Intervencao {
+            acao,
+            expira_em,
+            motivo: None,
+        })
+    }
+
+    #[test]
+    fn pedido_exige_duracao_e_acao_do_alvo() {
+        let agora = DateTime::from_timestamp(1_000, 0).unwrap();
+        let pedido = |acao, duracao_secs| PedidoIntervencao {
+            acao,
+            duracao_secs,
+            motivo: None,
+        };
+
+        let drenar = pedido(Some(Acao::Drenar), 60)
+            .validar(false, agora)
+            .unwrap();
+        assert_eq!(drenar.acao, Acao::Drenar);
+        assert_eq!(
+            drenar.expira_em,
+            DateTime::from_timestamp(1_060, 0).unwrap()
+        );
+        assert_eq!(
+            pedido(None, 60).validar(true, agora).unwrap().acao,
+            Acao::Pausar
+        );
+
+        assert!(pedido(None, 60).validar(false, agora).is_err());
+        assert!(
+            pedido(Some(Acao::Pausar), 60)
+                .validar(false, agora)
+                .is_err()
+        );
+        assert!(pedido(Some(Acao::Forcar), 60).validar(true, agora).is_err());
+        assert!(pedido(Some(Acao::Drenar), 0).validar(false, agora).is_err());
+    }
+
+    #[test]
+    fn expira_sozinha() {
+        let t0 = DateTime::from_timestamp(1_000, 0).unwrap();
+        let fim = DateTime::from_timestamp(1_060, 0).unwrap();
+        let mut controle = Controle::default();
+        assert!(controle.ativa(t0).is_none());
+
+        assert!(controle.alterar(intervencao(Acao::Drenar, fim), t0));
+        assert_eq!(controle.ativa(t0).map(|i| i.acao), Some(Acao::Drenar));
+        assert!(controle.ativa(fim).is_none());
+    }
+
+    #[test]
+    fn mudanca_atrasada_e_descartada() {
+        let t0 = DateTime::from_timestamp(1_000, 0).unwrap();
+        let t1 = DateTime::from_timestamp(1_001, 0).unwrap();
+        let fim = DateTime::from_timestamp(2_000, 0).unwrap();
+        let mut controle = Controle::default();
+
+        // Remoção em t1 chega antes da definição feita em t0.
+        assert!(controle.alterar(None, t1));
+        assert!(!controle.alterar(intervencao(Acao::Forcar, fim), t0));
+        assert!(controle.ativa(t0).is_none());
+
+        assert!(controle.alterar(intervencao(Acao::Saudavel, fim), t1));
+        assert_eq!(controle.ativa(t1).map(|i| i.acao), Some(Acao::Saudavel));
+    }
+}
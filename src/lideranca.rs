@@ -0,0 +1,159 @@
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{configuracao::duracao_ms, constantes};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigLideranca {
+    // Validade da concessão no Redis; sem renovação nesse prazo, outra instância assume.
+    #[serde(rename = "concessao_ms", with = "duracao_ms")]
+    pub concessao: Duration,
+    #[serde(rename = "renovacao_ms", with = "duracao_ms")]
+    pub renovacao: Duration,
+}
+
+impl Default for ConfigLideranca {
+    fn default() -> Self {
+        Self {
+            concessao: Duration::from_millis(constantes::LIDER_CONCESSAO_MS),
+            renovacao: Duration::from_millis(constantes::LIDER_RENOVACAO_MS),
+        }
+    }
+}
+
+impl ConfigLideranca {
+    pub fn validar(&self) -> Result<(), String> {
+        if self.concessao < Duration::from_millis(100) {
+            return Err("lideranca.concessao_ms deve ser de pelo menos 100".to_string());
+        }
+        // Renovar depois de metade da concessão deixaria a liderança expirar com um atraso só.
+        if self.renovacao < Duration::from_millis(10) || self.renovacao > self.concessao / 2 {
+            return Err(
+                "lideranca.renovacao_ms deve ficar entre 10 e metade de concessao_ms".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+// Quem coleta a saúde dos processadores é o dono da concessão no Redis. Cada nova
+// concessão recebe um token de fencing maior que todos os anteriores; mensagens de saúde
+// levam o token, e as de um líder antigo (que ainda não percebeu que perdeu) são ignoradas.
+pub struct Lideranca {
+    // Identifica este processo na concessão: HOSTNAME sozinho se repetiria após um restart.
+    pub identidade: String,
+    pub config: ConfigLideranca,
+    estado: Mutex<EstadoLideranca>,
+    maior_token: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EstadoLideranca {
+    // 0 = seguidor.
+    token: u64,
+    // Contado a partir do início da última disputa bem-sucedida, antes da resposta do
+    // Redis: o relógio local nunca acha que a concessão dura mais do que no servidor.
+    valida_ate: Instant,
+}
+
+impl Lideranca {
+    pub fn new(identidade: String, config: ConfigLideranca) -> Self {
+        Self {
+            identidade,
+            config,
+            estado: Mutex::new(EstadoLideranca {
+                token: 0,
+                valida_ate: Instant::now(),
+            }),
+            maior_token: AtomicU64::new(0),
+        }
+    }
+
+    // Resultado de uma disputa iniciada em `inicio`: token > 0 quando a concessão é nossa.
+    pub fn registrar_disputa(&self, token: u64, inicio: Instant) {
+        let mut estado = self.estado.lock().unwrap();
+        estado.token = token;
+        estado.valida_ate = inicio + self.config.concessao;
+        if token > 0 {
+            self.maior_token.fetch_max(token, Ordering::Relaxed);
+        }
+    }
+
+    pub fn renunciar(&self) {
+        self.estado.lock().unwrap().token = 0;
+    }
+
+    // Token atual se ainda for líder em `agora`; sem renovação a liderança cai sozinha.
+    pub fn token_vigente(&self, agora: Instant) -> Option<u64> {
+        let estado = self.estado.lock().unwrap();
+        (estado.token > 0 && agora < estado.valida_ate).then_some(estado.token)
+    }
+
+    pub fn e_lider(&self) -> bool {
+        self.token_vigente(Instant::now()).is_some()
+    }
+
+    // Fencing das mensagens de saúde: aceita o token mais novo já visto ou um maior.
+    pub fn aceitar_token(&self, token: u64) -> bool {
+        token > 0 && self.maior_token.fetch_max(token, Ordering::Relaxed) <= token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lideranca() -> Lideranca {
+        Lideranca::new(
+            "api01-1".to_string(),
+            ConfigLideranca {
+                concessao: Duration::from_millis(1000),
+                renovacao: Duration::from_millis(300),
+            },
+        )
+    }
+
+    #[test]
+    fn lideranca_expira_sem_renovacao() {
+        let lideranca = lideranca();
+        let inicio = Instant::now();
+        assert_eq!(lideranca.token_vigente(inicio), None);
+
+        lideranca.registrar_disputa(7, inicio);
+        assert_eq!(lideranca.token_vigente(inicio), Some(7));
+        assert_eq!(
+            lideranca.token_vigente(inicio + Duration::from_millis(999)),
+            Some(7)
+        );
+        assert_eq!(
+            lideranca.token_vigente(inicio + Duration::from_millis(1000)),
+            None
+        );
+
+        lideranca.registrar_disputa(0, inicio);
+        assert_eq!(lideranca.token_vigente(inicio), None);
+    }
+
+    #[test]
+    fn fencing_rejeita_token_de_lider_antigo() {
+        let lideranca = lideranca();
+        assert!(!lideranca.aceitar_token(0));
+        assert!(lideranca.aceitar_token(3));
+        assert!(lideranca.aceitar_token(3));
+        assert!(lideranca.aceitar_token(5));
+        assert!(!lideranca.aceitar_token(4));
+
+        // A própria concessão também avança o token mínimo aceito.
+        lideranca.registrar_disputa(9, Instant::now());
+        assert!(!lideranca.aceitar_token(5));
+        assert!(lideranca.aceitar_token(9));
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, ready},
+};
+
+use futures::future::BoxFuture;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower::{Layer, Service};
+
+// Semáforo com capacidade ajustável em tempo de execução. Ao reduzir com permissões em
+// uso, a diferença vira dívida: as próximas permissões devolvidas são descartadas até
+// quitá-la, sem interromper quem já entrou.
+#[derive(Debug)]
+pub struct LimiteConcorrencia {
+    semaforo: Arc<Semaphore>,
+    capacidade: Mutex<usize>,
+    divida: AtomicUsize,
+}
+
+pub struct Permissao {
+    permit: Option<OwnedSemaphorePermit>,
+    limite: Arc<LimiteConcorrencia>,
+}
+
+impl Drop for Permissao {
+    fn drop(&mut self) {
+        let quitou = self
+            .limite
+            .divida
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |d| d.checked_sub(1))
+            .is_ok();
+        if let Some(permit) = self.permit.take()
+            && quitou
+        {
+            permit.forget();
+        }
+    }
+}
+
+impl LimiteConcorrencia {
+    // 0 = sem limite.
+    pub fn new(capacidade: usize) -> Arc<Self> {
+        let capacidade = Self::efetiva(capacidade);
+        Arc::new(Self {
+            semaforo: Arc::new(Semaphore::new(capacidade)),
+            capacidade: Mutex::new(capacidade),
+            divida: AtomicUsize::new(0),
+        })
+    }
+
+    fn efetiva(capacidade: usize) -> usize {
+        match capacidade {
+            0 => Semaphore::MAX_PERMITS,
+            n => n.min(Semaphore::MAX_PERMITS),
+        }
+    }
+
+    pub fn capacidade(&self) -> usize {
+        *self.capacidade.lock().unwrap()
+    }
+
+    pub fn disponiveis(&self) -> usize {
+        self.semaforo.available_permits()
+    }
+
+    pub fn ajustar(&self, nova: usize) {
+        let nova = Self::efetiva(nova);
+        let mut capacidade = self.capacidade.lock().unwrap();
+        if nova > *capacidade {
+            // Aumento primeiro cancela dívida de uma redução anterior.
+            let mut acrescimo = nova - *capacidade;
+            let _ = self
+                .divida
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |d| {
+                    let quitado = d.min(acrescimo);
+                    acrescimo -= quitado;
+                    Some(d - quitado)
+                });
+            self.semaforo.add_permits(acrescimo);
+        } else {
+            let excesso = *capacidade - nova;
+            let descartadas = self.semaforo.forget_permits(excesso);
+            self.divida
+                .fetch_add(excesso - descartadas, Ordering::AcqRel);
+        }
+        *capacidade = nova;
+    }
+
+    pub fn tentar(self: &Arc<Self>) -> Option<Permissao> {
+        let permit = self.semaforo.clone().try_acquire_owned().ok()?;
+        Some(Permissao {
+            permit: Some(permit),
+            limite: self.clone(),
+        })
+    }
+
+    pub async fn aguardar(self: &Arc<Self>) -> Permissao {
+        // O semáforo nunca é fechado.
+        let permit = self.semaforo.clone().acquire_owned().await.unwrap();
+        Permissao {
+            permit: Some(permit),
+            limite: self.clone(),
+        }
+    }
+}
+
+// Substitui o `ConcurrencyLimitLayer` nas rotas: o limite é compartilhado por todas as
+// rotas do router e pode mudar com uma recarga de configuração. Como ele, só fica pronto
+// com uma permissão em mãos, então o `BufferLayer` acima segura no máximo `buffer`
+// requisições esperando vaga.
+#[derive(Clone)]
+pub struct LimiteLayer {
+    limite: Arc<LimiteConcorrencia>,
+}
+
+impl LimiteLayer {
+    pub fn new(limite: Arc<LimiteConcorrencia>) -> Self {
+        Self { limite }
+    }
+}
+
+impl<S> Layer<S> for LimiteLayer {
+    type Service = Limitado<S>;
+
+    fn layer(&self, interno: S) -> Self::Service {
+        Limitado {
+            interno,
+            limite: self.limite.clone(),
+            aguardando: None,
+            permissao: None,
+        }
+    }
+}
+
+pub struct Limitado<S> {
+    interno: S,
+    limite: Arc<LimiteConcorrencia>,
+    aguardando: Option<BoxFuture<'static, Permissao>>,
+    permissao: Option<Permissao>,
+}
+
+// Cada clone espera a própria permissão.
+impl<S: Clone> Clone for Limitado<S> {
+    fn clone(&self) -> Self {
+        Self {
+            interno: self.interno.clone(),
+            limite: self.limite.clone(),
+            aguardando: None,
+            permissao: None,
+        }
+    }
+}
+
+impl<S, R> Service<R> for Limitado<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.permissao.is_none() {
+            let limite = self.limite.clone();
+            let aguardando = self
+                .aguardando
+                .get_or_insert_with(|| Box::pin(async move { limite.aguardar().await }));
+            self.permissao = Some(ready!(Pin::new(aguardando).poll(cx)));
+            self.aguardando = None;
+        }
+        self.interno.poll_ready(cx)
+    }
+
+    fn call(&mut self, requisicao: R) -> Self::Future {
+        let permissao = self.permissao.take().expect("call sem poll_ready pronto");
+        let resposta = self.interno.call(requisicao);
+        Box::pin(async move {
+            let resposta = resposta.await;
+            drop(permissao);
+            resposta
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reducao_com_permissoes_em_uso_vira_divida() {
+        let limite = LimiteConcorrencia::new(3);
+        let a = limite.tentar().unwrap();
+        let b = limite.tentar().unwrap();
+
+        limite.ajustar(1);
+        assert_eq!(limite.disponiveis(), 0);
+        drop(a);
+        // Descartada para quitar a dívida.
+        assert_eq!(limite.disponiveis(), 0);
+        drop(b);
+        assert_eq!(limite.disponiveis(), 1);
+
+        limite.ajustar(4);
+        assert_eq!(limite.disponiveis(), 4);
+        assert_eq!(limite.capacidade(), 4);
+    }
+
+    #[test]
+    fn aumento_cancela_divida_pendente() {
+        let limite = LimiteConcorrencia::new(2);
+        let a = limite.tentar().unwrap();
+        let b = limite.tentar().unwrap();
+        limite.ajustar(1);
+        limite.ajustar(2);
+        drop(a);
+        drop(b);
+        assert_eq!(limite.disponiveis(), 2);
+
+        let sem_limite = LimiteConcorrencia::new(0);
+        assert_eq!(sem_limite.disponiveis(), Semaphore::MAX_PERMITS);
+        sem_limite.ajustar(5);
+        assert_eq!(sem_limite.disponiveis(), 5);
+    }
+
+    #[tokio::test]
+    async fn servico_so_fica_pronto_com_permissao() {
+        let limite = LimiteConcorrencia::new(1);
+        let interno = tower::service_fn(|n: u32| async move { Ok::<_, ()>(n) });
+        let mut a = LimiteLayer::new(limite.clone()).layer(interno);
+        let mut b = a.clone();
+        let pronto = |servico: &mut Limitado<_>| {
+            let mut cx = Context::from_waker(std::task::Waker::noop());
+            servico.poll_ready(&mut cx).is_ready()
+        };
+
+        assert!(pronto(&mut a));
+        let resposta = a.call(1);
+        // A permissão fica com a requisição até ela terminar.
+        assert!(!pronto(&mut b));
+        assert_eq!(resposta.await, Ok(1));
+        assert!(pronto(&mut b));
+        assert_eq!(limite.disponiveis(), 0);
+    }
+}
@@ -1,127 +1,303 @@
 #[global_allocator]
 static GLOBAL: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
+mod admissao;
 mod api;
 mod appstate;
+mod armazenamento;
+mod circuit_breaker;
+mod configuracao;
 mod constantes;
+mod encerramento;
+mod estatisticas;
+mod intervencao;
+mod lideranca;
+mod limite;
+mod metricas;
 mod models;
+mod recarga;
+mod roteamento;
+mod telemetria;
 mod workers;
 use crate::{
     api::{
+        fila::{self, BackendFila},
         handler::{self},
         http::cria_cliente_http,
         nats::cria_cliente_nats,
-        redis::{coletar_entre_timestamp, estabelecer_pool_conexao},
+        redis::estabelecer_pool_conexao,
     },
     appstate::AppState,
-    models::processor::{Processor, TipoProcessador},
-    workers::{consumer, health_checker, health_consumer},
+    configuracao::{Argumentos, Configuracao},
+    encerramento::Encerramento,
+    lideranca::Lideranca,
+    limite::LimiteLayer,
+    metricas::Metricas,
+    models::processor::{Processor, ordenar_registro},
+    recarga::Recarga,
+    workers::{
+        carga, compactacao, consumer, eleicao, health_checker, health_consumer, intervencoes,
+        recarga_config, resultados, sobras,
+    },
 };
 use axum::{
     Router,
     body::Bytes,
     error_handling::HandleErrorLayer,
-    routing::{get, post},
+    middleware,
+    routing::{get, post, put},
 };
 
+use chrono::Utc;
 use std::{
-    env,
-    sync::{Arc, atomic::AtomicUsize},
+    env, process,
+    sync::{Arc, Mutex, atomic::AtomicUsize},
+    time::Duration,
 };
 use tokio::sync::{Semaphore, mpsc};
-use tower::{ServiceBuilder, buffer::BufferLayer, limit::ConcurrencyLimitLayer};
+use tower::{ServiceBuilder, buffer::BufferLayer};
 
 #[tokio::main(worker_threads = 4)]
 async fn main() {
-    let processador_default = Processor::new_async(
-        false,
-        100,
-        env::var("URL_DEFAULT").unwrap_or_else(|_| constantes::URL_DEFAULT.to_string()),
-        TipoProcessador::Default,
-    );
-    let processador_fallback = Processor::new_async(
-        false,
-        100,
-        env::var("URL_FALLBACK").unwrap_or_else(|_| constantes::URL_FALLBACK.to_string()),
-        TipoProcessador::Fallback,
-    );
-
-    let vc_proc = vec![processador_default, processador_fallback];
+    let argumentos = Argumentos::ler(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        process::exit(2);
+    });
+    let config = Configuracao::carregar(argumentos.arquivo.as_deref()).unwrap_or_else(|e| {
+        eprintln!("❌ Configuração inválida:\n{}", e);
+        process::exit(1);
+    });
+    if argumentos.imprimir {
+        print!("{}", config.para_toml());
+        return;
+    }
+    let telemetria = telemetria::iniciar(&config.log);
 
-    let num_workers = (env::var("NUM_CONSUMER")
-        .unwrap_or_else(|_| constantes::NUM_CONSUMER.to_string()))
-    .parse()
-    .unwrap();
+    let vc_proc: Vec<_> = ordenar_registro(config.processadores.clone())
+        .into_iter()
+        .map(|processador| Processor::new_async(processador, config.circuito, &config.estatisticas))
+        .collect();
 
+    let num_workers = config.fila.workers;
     let mut senders = Vec::with_capacity(num_workers);
     let mut receivers = Vec::with_capacity(num_workers);
 
     for _ in 0..num_workers {
-        let (sender, receiver) = mpsc::channel::<Bytes>(300);
+        let (sender, receiver) = mpsc::channel::<Bytes>(config.fila.capacidade_canal);
         senders.push(sender);
         receivers.push(receiver);
     }
-    let retry_percentage = env::var("RETRY_DEFAULT_PERCENTAGE")
-        .unwrap_or_else(|_| "75.0".to_string())
-        .parse()
-        .unwrap_or(75.0);
+    // Estratégia já validada junto com a configuração.
+    let recarga = Arc::new(
+        Recarga::new(config.clone(), argumentos.arquivo.clone())
+            .unwrap_or_else(|e| panic!("❌ {}", e)),
+    );
+    let backend_fila = config.fila.backend;
 
-    let nats_client = cria_cliente_nats().await;
+    let nats_client = cria_cliente_nats(&config.nats.url).await;
+    let redis_pool = estabelecer_pool_conexao(&config.redis, num_workers).await;
+    let retencao = config.armazenamento.retencao();
+    let armazenamento =
+        armazenamento::criar_armazenamento(&config.armazenamento, redis_pool.clone())
+            .await
+            .unwrap_or_else(|e| panic!("❌ Armazenamento inválido: {}", e));
+    let instancia = config.servidor.instancia.clone();
     let app_state = AppState {
-        http_client: cria_cliente_http(),
+        http_client: cria_cliente_http(&config.pagamentos),
         processors: vc_proc,
-        redis_pool: estabelecer_pool_conexao().await,
-        nats_client: nats_client,
+        redis_pool,
+        nats_client,
         sender_queue: Arc::new(senders),
         round_robin_counter: Arc::new(AtomicUsize::new(0)),
-        fast_furious: Arc::new(Semaphore::new(100)),
-        retry_default_percentage: retry_percentage,
+        fast_furious: Arc::new(Semaphore::new(config.fila.permissoes_diretas as usize)),
+        fila: config.fila,
+        recarga: recarga.clone(),
+        iniciado_em: Utc::now(),
+        pausa: Arc::new(Mutex::new(Default::default())),
+        carga: Arc::new(Mutex::new(Default::default())),
+        armazenamento,
+        retencao,
+        instancia: Arc::from(instancia.as_str()),
+        admin_token: config.servidor.admin_token.as_deref().map(Arc::from),
+        metricas: Arc::new(Metricas::new()),
+        lideranca: Arc::new(Lideranca::new(
+            format!("{}-{}", instancia, Utc::now().timestamp_micros()),
+            config.lideranca,
+        )),
+        saude: config.saude,
+        estatisticas: config.estatisticas,
+        encerramento: Arc::new(Encerramento::new(config.encerramento.prazo)),
+        lote: config.lote,
+        admissao: config.admissao,
     };
     api::redis::pre_aquecer_pool_redis(&app_state.redis_pool, num_workers).await;
-    let _ = coletar_entre_timestamp(&app_state.clone(), 0, u64::MAX).await;
-    for receiver in receivers.into_iter() {
-        tokio::spawn(Box::pin(consumer::worker_processa_pagamento(
-            app_state.clone(),
-            receiver,
-        )));
-    }
-
-    match env::var("ROLE")
-        .unwrap_or_else(|_| "LIDER".to_string())
-        .as_str()
-    {
-        "LIDER" => {
-            tokio::spawn(health_checker::cria_worker_coleta_saude(app_state.clone()));
+    let _ = app_state.armazenamento.sumario(0, u64::MAX).await;
+    // Tarefas que o encerramento espera terminar.
+    let mut drenagem = Vec::new();
+    match backend_fila {
+        BackendFila::Memoria => {
+            for receiver in receivers.into_iter() {
+                drenagem.push(tokio::spawn(Box::pin(consumer::worker_processa_pagamento(
+                    app_state.clone(),
+                    receiver,
+                ))));
+            }
+            tokio::spawn(sobras::cria_worker_recupera_sobras(app_state.clone()));
         }
-        _ => {
-            tokio::spawn(health_consumer::cria_worker_confere_saude(
-                app_state.clone(),
-            ));
+        BackendFila::RedisStreams => {
+            fila::criar_grupo_consumidores(&app_state)
+                .await
+                .expect("❌ Não foi possível criar o grupo de consumidores do Redis Stream.");
+
+            // Nome estável por instância/worker para que, ao reiniciar, cada worker
+            // recupere as entradas que recebeu e não confirmou.
+            for indice in 0..num_workers {
+                drenagem.push(tokio::spawn(Box::pin(
+                    consumer::worker_processa_fila_duravel(
+                        app_state.clone(),
+                        format!("{}-{}", app_state.instancia, indice),
+                    ),
+                )));
+            }
         }
     }
 
+    if retencao.retencao_secs > 0 {
+        tokio::spawn(compactacao::cria_worker_compactacao(app_state.clone()));
+    }
+
+    // Todas as instâncias disputam a liderança; só a líder do momento consulta os
+    // processadores, e todas aplicam a saúde publicada por ela.
+    health_consumer::carregar_saude_persistida(&app_state).await;
+    intervencao::carregar_intervencoes_persistidas(&app_state).await;
+    drenagem.push(tokio::spawn(eleicao::cria_worker_eleicao(
+        app_state.clone(),
+    )));
+    tokio::spawn(health_checker::cria_worker_coleta_saude(app_state.clone()));
+    tokio::spawn(health_consumer::cria_worker_confere_saude(
+        app_state.clone(),
+    ));
+    tokio::spawn(resultados::cria_worker_publica_resultados(
+        app_state.clone(),
+    ));
+    tokio::spawn(resultados::cria_worker_recebe_resultados(app_state.clone()));
+    tokio::spawn(recarga_config::cria_worker_recarga(app_state.clone()));
+    tokio::spawn(intervencoes::cria_worker_intervencoes(app_state.clone()));
+    if app_state.admissao.habilitada {
+        tokio::spawn(carga::cria_worker_mede_carga(app_state.clone()));
+    }
+
+    let admin_router = Router::new()
+        .route("/purge-payments", post(handler::purge_payments))
+        .route("/admin/consistencia", get(handler::verificar_consistencia))
+        .route("/admin/processadores", get(handler::listar_processadores))
+        .route(
+            "/admin/processadores/{nome}/intervencao",
+            put(handler::definir_intervencao).delete(handler::remover_intervencao),
+        )
+        .route(
+            "/admin/pausa",
+            get(handler::exibir_pausa)
+                .put(handler::pausar)
+                .delete(handler::retomar),
+        )
+        .route(
+            "/admin/config",
+            get(handler::exibir_configuracao).put(handler::recarregar_configuracao),
+        )
+        .route("/admin/dead-letters", get(handler::listar_dead_letters))
+        .route(
+            "/admin/dead-letters/{id}",
+            get(handler::buscar_dead_letter).delete(handler::descartar_dead_letter),
+        )
+        .route(
+            "/admin/dead-letters/{id}/replay",
+            post(handler::reprocessar_dead_letter),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            handler::exigir_token_admin,
+        ));
+
     let high_priority_router = Router::new()
         .route("/payments-summary", get(handler::get_payment_summary))
-        .route("/purge-payments", post(handler::purge_payments))
+        .route("/payments/{id}", get(handler::buscar_status_pagamento))
+        .merge(admin_router)
         .layer(
             ServiceBuilder::new()
                 .layer(HandleErrorLayer::new(handler::handle_tower_error))
-                .layer(BufferLayer::new(16))
-                .layer(ConcurrencyLimitLayer::new(16)),
+                .layer(BufferLayer::new(config.servidor.prioritario.buffer))
+                .layer(LimiteLayer::new(recarga.limite_prioritario.clone())),
         );
 
     let low_priority_router = Router::new()
         .route("/payments", post(handler::submit_work_handler))
+        .route("/payments/batch", post(handler::submit_batch_handler))
         .layer(
             ServiceBuilder::new()
                 .layer(HandleErrorLayer::new(handler::handle_tower_error))
-                .layer(BufferLayer::new(1024 * 6))
-                .layer(ConcurrencyLimitLayer::new(800)),
+                .layer(BufferLayer::new(config.servidor.pagamentos.buffer))
+                .layer(LimiteLayer::new(recarga.limite_pagamentos.clone())),
         );
+    // Porta separada para o scrape não disputar os limites de concorrência de /payments.
+    let admin_port = config.servidor.porta_admin;
+    let admin_app = Router::new()
+        .route("/metrics", get(handler::exportar_metricas))
+        .with_state(app_state.clone());
+    let admin_listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", admin_port))
+        .await
+        .unwrap_or_else(|e| {
+            panic!(
+                "❌ Não foi possível abrir a porta admin {}: {}",
+                admin_port, e
+            )
+        });
+    tokio::spawn(async move { axum::serve(admin_listener, admin_app).await });
+
+    // Fora dos limites de concorrência: o balanceador precisa da resposta mesmo com as
+    // rotas saturadas.
+    let sondas_router = Router::new()
+        .route("/health", get(handler::vivo))
+        .route("/ready", get(handler::prontidao));
+
+    let estado = app_state.clone();
     let app = high_priority_router
         .merge(low_priority_router)
+        .merge(sondas_router)
         .with_state(app_state);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:9999").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let porta = config.servidor.porta;
+    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", porta))
+        .await
+        .unwrap_or_else(|e| panic!("❌ Não foi possível abrir a porta {}: {}", porta, e));
+    tracing::info!(porta, porta_admin = admin_port, "servidor iniciado");
+    // Com o sinal, para de aceitar conexões e termina as requisições em andamento.
+    axum::serve(listener, app)
+        .with_graceful_shutdown(encerramento::sinal_encerramento())
+        .await
+        .unwrap();
+
+    estado.encerramento.iniciar();
+    tracing::info!(
+        prazo_ms = estado.encerramento.prazo.as_millis() as u64,
+        "encerrando: drenando pagamentos"
+    );
+    let aguardar = async {
+        for tarefa in drenagem {
+            let _ = tarefa.await;
+        }
+        // Todas as permissões de volta = nenhuma tarefa direta (handler ou sobras) em andamento.
+        let _ = estado
+            .fast_furious
+            .acquire_many(estado.fila.permissoes_diretas)
+            .await;
+    };
+    // Depois do prazo, cada tarefa ainda termina a requisição em curso ao processador
+    // antes de guardar o pagamento.
+    let limite = estado.encerramento.prazo + estado.pagamentos().timeout + Duration::from_secs(1);
+    if tokio::time::timeout(limite, aguardar).await.is_err() {
+        tracing::warn!("encerramento sem terminar a drenagem");
+    }
+    tracing::info!("encerrado");
+    telemetria.encerrar();
 }
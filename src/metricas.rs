@@ -0,0 +1,238 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+use chrono::Utc;
+
+use crate::{appstate::AppState, circuit_breaker::EstadoCircuito};
+
+// Contadores e histogramas são atualizados no caminho do pagamento; os gauges (fila,
+// semáforo, saúde, circuito e liderança) são lidos do estado só na hora do scrape, então
+// valem também na instância que recebe a saúde via NATS.
+pub struct Metricas {
+    registro: Registry,
+    pub recebidos: IntCounterVec,
+    pub descartados: IntCounterVec,
+    pub confirmados: IntCounterVec,
+    pub fallback: IntCounterVec,
+    pub tentativas: Histogram,
+    pub requisicoes_processador: IntCounterVec,
+    pub latencia_processador: HistogramVec,
+    pub gravacao: Histogram,
+    pub redis: HistogramVec,
+    pub saude_erros: IntCounterVec,
+    saude_falhando: IntGaugeVec,
+    saude_min_response_ms: IntGaugeVec,
+    saude_idade_segundos: IntGaugeVec,
+    profundidade_fila: IntGaugeVec,
+    fast_furious_livres: IntGauge,
+    circuito: IntGaugeVec,
+    slots_livres: IntGaugeVec,
+    lider: IntGauge,
+}
+
+impl Metricas {
+    pub fn new() -> Self {
+        let registro = Registry::new_custom(Some("rinha".to_string()), None)
+            .expect("❌ Prefixo de métricas inválido.");
+
+        fn registrar<M: prometheus::core::Collector + Clone + 'static>(
+            registro: &Registry,
+            metrica: M,
+        ) -> M {
+            registro
+                .register(Box::new(metrica.clone()))
+                .expect("❌ Métrica registrada duas vezes.");
+            metrica
+        }
+        fn contador(
+            registro: &Registry,
+            nome: &str,
+            ajuda: &str,
+            rotulos: &[&str],
+        ) -> IntCounterVec {
+            registrar(
+                registro,
+                IntCounterVec::new(Opts::new(nome, ajuda), rotulos).unwrap(),
+            )
+        }
+        fn gauge(registro: &Registry, nome: &str, ajuda: &str, rotulos: &[&str]) -> IntGaugeVec {
+            registrar(
+                registro,
+                IntGaugeVec::new(Opts::new(nome, ajuda), rotulos).unwrap(),
+            )
+        }
+        fn latencia(nome: &str, ajuda: &str) -> HistogramOpts {
+            HistogramOpts::new(nome, ajuda).buckets(vec![
+                0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+            ])
+        }
+
+        Self {
+            recebidos: contador(
+                &registro,
+                "pagamentos_recebidos_total",
+                "POST /payments por resultado da admissão.",
+                &["resultado"],
+            ),
+            descartados: contador(
+                &registro,
+                "pagamentos_descartados_total",
+                "Pagamentos que saíram do fluxo sem confirmação.",
+                &["motivo"],
+            ),
+            confirmados: contador(
+                &registro,
+                "pagamentos_confirmados_total",
+                "Pagamentos confirmados e gravados, por processador.",
+                &["processador"],
+            ),
+            fallback: contador(
+                &registro,
+                "pagamentos_fallback_total",
+                "Confirmações fora do nível de prioridade principal.",
+                &["processador"],
+            ),
+            tentativas: registrar(
+                &registro,
+                Histogram::with_opts(
+                    HistogramOpts::new(
+                        "pagamento_tentativas",
+                        "Tentativas gastas por pagamento até o desfecho.",
+                    )
+                    .buckets(vec![0.0, 1.0, 2.0, 3.0, 5.0, 10.0, 20.0, 30.0, 40.0]),
+                )
+                .unwrap(),
+            ),
+            requisicoes_processador: contador(
+                &registro,
+                "processador_requisicoes_total",
+                "Respostas dos processadores por status HTTP (\"erro\" = sem resposta).",
+                &["processador", "status"],
+            ),
+            latencia_processador: registrar(
+                &registro,
+                HistogramVec::new(
+                    latencia(
+                        "processador_latencia_segundos",
+                        "Duração do POST /payments no processador.",
+                    ),
+                    &["processador"],
+                )
+                .unwrap(),
+            ),
+            gravacao: registrar(
+                &registro,
+                Histogram::with_opts(latencia(
+                    "pagamento_gravacao_segundos",
+                    "Gravação do pagamento confirmado, incluindo retries.",
+                ))
+                .unwrap(),
+            ),
+            redis: registrar(
+                &registro,
+                HistogramVec::new(
+                    latencia(
+                        "redis_operacao_segundos",
+                        "Operações de status e dedup no Redis.",
+                    ),
+                    &["operacao"],
+                )
+                .unwrap(),
+            ),
+            saude_falhando: gauge(
+                &registro,
+                "processador_saude_falhando",
+                "Último `failing` conhecido (1 = falhando).",
+                &["processador"],
+            ),
+            saude_min_response_ms: gauge(
+                &registro,
+                "processador_saude_min_response_ms",
+                "Último `minResponseTime` conhecido.",
+                &["processador"],
+            ),
+            saude_idade_segundos: gauge(
+                &registro,
+                "processador_saude_idade_segundos",
+                "Idade da última saúde conhecida (-1 = nenhuma ainda).",
+                &["processador"],
+            ),
+            saude_erros: contador(
+                &registro,
+                "processador_saude_erros_total",
+                "Health checks sem resposta válida.",
+                &["processador"],
+            ),
+            profundidade_fila: gauge(
+                &registro,
+                "fila_profundidade",
+                "Mensagens aguardando em cada canal de worker.",
+                &["canal"],
+            ),
+            fast_furious_livres: registrar(
+                &registro,
+                IntGauge::new(
+                    "fast_furious_livres",
+                    "Permissões livres para processar direto no handler.",
+                )
+                .unwrap(),
+            ),
+            circuito: gauge(
+                &registro,
+                "processador_circuito",
+                "Disjuntor: 0 = fechado, 1 = meio-aberto, 2 = aberto.",
+                &["processador"],
+            ),
+            slots_livres: gauge(
+                &registro,
+                "processador_slots_livres",
+                "Requisições simultâneas ainda disponíveis no processador.",
+                &["processador"],
+            ),
+            lider: registrar(
+                &registro,
+                IntGauge::new("lider", "1 enquanto esta instância detém a liderança.").unwrap(),
+            ),
+            registro,
+        }
+    }
+
+    pub async fn renderizar(&self, state: &AppState) -> String {
+        for (canal, sender) in state.sender_queue.iter().enumerate() {
+            self.profundidade_fila
+                .with_label_values(&[&canal.to_string()])
+                .set((sender.max_capacity() - sender.capacity()) as i64);
+        }
+        self.fast_furious_livres
+            .set(state.fast_furious.available_permits() as i64);
+        self.lider.set(state.lideranca.e_lider() as i64);
+        let agora = Utc::now();
+        for processor in state.processors.iter() {
+            let guard = processor.read().await;
+            self.saude_idade_segundos
+                .with_label_values(&[&guard.nome])
+                .set(guard.saude_em.map_or(-1, |em| (agora - em).num_seconds()));
+            self.saude_falhando
+                .with_label_values(&[&guard.nome])
+                .set(guard.failing as i64);
+            self.saude_min_response_ms
+                .with_label_values(&[&guard.nome])
+                .set(guard.min_response_time as i64);
+            let estado = match guard.disjuntor.estado() {
+                EstadoCircuito::Fechado => 0,
+                EstadoCircuito::MeioAberto { .. } => 1,
+                EstadoCircuito::Aberto { .. } => 2,
+            };
+            self.circuito.with_label_values(&[&guard.nome]).set(estado);
+            self.slots_livres
+                .with_label_values(&[&guard.nome])
+                .set(guard.limite.disponiveis().min(i64::MAX as usize) as i64);
+        }
+
+        let mut saida = Vec::new();
+        let _ = TextEncoder::new().encode(&self.registro.gather(), &mut saida);
+        String::from_utf8(saida).unwrap_or_default()
+    }
+}
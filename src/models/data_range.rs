@@ -5,3 +5,17 @@ pub struct DateRangeParams {
     pub from: Option<DateTime<Utc>>,
     pub to: Option<DateTime<Utc>>,
 }
+
+impl DateRangeParams {
+    pub fn intervalo_micros(&self) -> (u64, u64) {
+        let from_ts = self
+            .from
+            .map(|dt| dt.timestamp_micros() as u64)
+            .unwrap_or(0);
+        let to_ts = self
+            .to
+            .map(|dt| dt.timestamp_micros() as u64)
+            .unwrap_or(u64::MAX);
+        (from_ts, to_ts)
+    }
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::payment::Payment;
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct DeadLetter {
+    pub payment: Payment,
+    #[serde(rename = "ultimoErro")]
+    pub ultimo_erro: String,
+    pub tentativas: u8,
+    pub processador: Option<String>,
+    // O processador já confirmou a cobrança e só a gravação falhou: o replay
+    // deve apenas regravar, nunca reenviar ao processador.
+    pub cobrado: bool,
+    // Nem o POST nem as consultas ao processador deram resposta definitiva: o replay
+    // precisa consultar `processador` antes de reenviar.
+    #[serde(default, rename = "resultadoDesconhecido")]
+    pub resultado_desconhecido: bool,
+    #[serde(rename = "registradoEm")]
+    pub registrado_em: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PaginacaoParams {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
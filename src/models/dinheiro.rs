@@ -0,0 +1,173 @@
+use std::{fmt, str::FromStr};
+
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+
+// Até 15 dígitos significativos todo decimal sobrevive à ida e volta por f64; acima
+// disso o valor em reais deixaria de ser exato na API.
+const MAX_CENTAVOS: i64 = 999_999_999_999_999;
+
+// Valor monetário em centavos. Na API (cliente e processadores) trafega como número em
+// reais (`19.9`); internamente, no Redis e nas somas, é sempre inteiro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Centavos(pub i64);
+
+impl Centavos {
+    // Mesma regra de `from_decimal`: mais de duas casas decimais é erro, não arredondamento.
+    // O f64 mais próximo de `x.yz`, vezes 100, fica a poucos ulps de `xyz`; `1.005` fica a
+    // meio centavo e é recusado.
+    pub fn from_reais_f64(reais: f64) -> Option<Self> {
+        if !reais.is_finite() || reais < 0.0 {
+            return None;
+        }
+        let bruto = reais * 100.0;
+        let centavos = bruto.round();
+        let tolerancia = (centavos * f64::EPSILON * 4.0).max(1e-9);
+        if (bruto - centavos).abs() > tolerancia || centavos > MAX_CENTAVOS as f64 {
+            return None;
+        }
+        Some(Centavos(centavos as i64))
+    }
+
+    pub fn from_decimal(valor: Decimal) -> Option<Self> {
+        if valor.is_sign_negative() || valor.round_dp(2) != valor {
+            return None;
+        }
+        (valor * Decimal::ONE_HUNDRED)
+            .to_i64()
+            .filter(|c| *c <= MAX_CENTAVOS)
+            .map(Centavos)
+    }
+
+    pub fn to_decimal(self) -> Decimal {
+        Decimal::new(self.0, 2)
+    }
+
+    // Só para heurísticas (ex.: custo estimado no roteamento), nunca para somar.
+    pub fn to_reais_f64(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+}
+
+impl Serialize for Centavos {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Divisão corretamente arredondada + impressão mais curta do serializador
+        // devolvem exatamente o decimal de duas casas.
+        serializer.serialize_f64(self.to_reais_f64())
+    }
+}
+
+impl<'de> Deserialize<'de> for Centavos {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CentavosVisitor;
+
+        impl Visitor<'_> for CentavosVisitor {
+            type Value = Centavos;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("valor em reais, positivo e com no máximo duas casas decimais")
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Centavos, E> {
+                Centavos::from_reais_f64(v).ok_or_else(|| E::custom("amount inválido"))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Centavos, E> {
+                v.checked_mul(100)
+                    .and_then(|c| i64::try_from(c).ok())
+                    .filter(|c| *c <= MAX_CENTAVOS)
+                    .map(Centavos)
+                    .ok_or_else(|| E::custom("amount fora do intervalo"))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Centavos, E> {
+                u64::try_from(v)
+                    .map_err(|_| E::custom("amount negativo"))
+                    .and_then(|v| self.visit_u64(v))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Centavos, E> {
+                Decimal::from_str(v)
+                    .ok()
+                    .and_then(Centavos::from_decimal)
+                    .ok_or_else(|| E::custom("amount inválido"))
+            }
+        }
+
+        deserializer.deserialize_any(CentavosVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn parse(json: &str) -> Option<Centavos> {
+        serde_json::from_str(json).ok()
+    }
+
+    #[test]
+    fn converte_valores_com_duas_casas_sem_perda() {
+        assert_eq!(parse("19.9"), Some(Centavos(1990)));
+        assert_eq!(parse("0.1"), Some(Centavos(10)));
+        assert_eq!(parse("0.29"), Some(Centavos(29)));
+        assert_eq!(parse("42"), Some(Centavos(4200)));
+        assert_eq!(parse("\"19.90\""), Some(Centavos(1990)));
+        assert_eq!(parse("9999999999999.99"), Some(Centavos(999999999999999)));
+    }
+
+    #[test]
+    fn rejeita_valores_invalidos() {
+        assert_eq!(parse("-1.5"), None);
+        assert_eq!(parse("\"1.001\""), None);
+        assert_eq!(parse("1.005"), None);
+        assert_eq!(parse("19.999"), None);
+        assert_eq!(parse("0.001"), None);
+        assert_eq!(parse("\"abc\""), None);
+        assert_eq!(parse("1e300"), None);
+        assert_eq!(parse("10000000000000"), None);
+    }
+
+    #[test]
+    fn serializa_como_reais_exatos() {
+        assert_eq!(serde_json::to_string(&Centavos(1990)).unwrap(), "19.9");
+        assert_eq!(serde_json::to_string(&Centavos(1)).unwrap(), "0.01");
+        assert_eq!(
+            serde_json::to_string(&Centavos(123456789012)).unwrap(),
+            "1234567890.12"
+        );
+    }
+
+    #[test]
+    fn ida_e_volta_preserva_todo_centavo() {
+        for c in (0..100_000).chain(MAX_CENTAVOS - 1000..=MAX_CENTAVOS) {
+            let json = serde_json::to_string(&Centavos(c)).unwrap();
+            assert_eq!(parse(&json), Some(Centavos(c)), "{}", json);
+        }
+    }
+
+    // A soma em centavos precisa bater com a soma decimal exata dos pagamentos
+    // individuais, onde somar f64 em reais já diverge.
+    #[test]
+    fn soma_em_centavos_bate_com_soma_decimal() {
+        let valores = ["19.90", "0.10", "0.20", "1234.56", "0.01", "99.99"];
+        let mut total_centavos = 0i64;
+        let mut total_decimal = Decimal::ZERO;
+        let mut total_f64 = 0f64;
+
+        for i in 0..1_000_000 {
+            let valor = valores[i % valores.len()];
+            total_centavos += parse(valor).unwrap().0;
+            total_decimal += Decimal::from_str(valor).unwrap();
+            total_f64 += valor.parse::<f64>().unwrap();
+        }
+
+        assert_eq!(Centavos(total_centavos).to_decimal(), total_decimal);
+        assert_eq!(total_decimal, dec!(225793684.92));
+        assert_ne!(Decimal::try_from(total_f64).unwrap(), total_decimal);
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    armazenamento::FiltroExpurgo,
+    models::{data_range::DateRangeParams, summary::Summary},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct ExpurgoParams {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub processador: Option<String>,
+}
+
+impl ExpurgoParams {
+    pub fn filtro(&self) -> FiltroExpurgo {
+        let (from, to) = DateRangeParams {
+            from: self.from,
+            to: self.to,
+        }
+        .intervalo_micros();
+        FiltroExpurgo {
+            from,
+            to,
+            processador: self.processador.clone(),
+        }
+    }
+}
+
+// Gravado na lista `purge_audit` a cada expurgo, e devolvido como resposta.
+#[derive(Serialize, Debug)]
+pub struct RegistroExpurgo {
+    #[serde(rename = "registradoEm")]
+    pub registrado_em: DateTime<Utc>,
+    pub instancia: String,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub processador: Option<String>,
+    // O que saiu do sumário, por processador.
+    pub pagamentos: BTreeMap<String, Summary>,
+    // Dedup, status, dead letters e sobras do encerramento apagados no Redis.
+    #[serde(rename = "chavesRemovidas")]
+    pub chaves_removidas: u64,
+}
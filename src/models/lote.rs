@@ -0,0 +1,133 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::payment::Payment;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultadoItem {
+    Aceito,
+    // O correlationId já tinha sido aceito (neste lote ou antes); não é processado de novo.
+    Duplicado,
+    Invalido,
+    // Válido, mas não coube na fila ou o Redis não respondeu; pode ser reenviado.
+    Rejeitado,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ItemLote {
+    pub indice: usize,
+    #[serde(rename = "correlationId", skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<Uuid>,
+    pub resultado: ResultadoItem,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub erro: Option<String>,
+    // Recusado pela admissão: quando vale a pena reenviar.
+    #[serde(rename = "retryAfterSecs", skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct RespostaLote {
+    pub aceitos: usize,
+    pub duplicados: usize,
+    pub invalidos: usize,
+    pub rejeitados: usize,
+    pub itens: Vec<ItemLote>,
+}
+
+impl RespostaLote {
+    pub fn new(itens: Vec<ItemLote>) -> Self {
+        let mut resposta = RespostaLote::default();
+        for item in &itens {
+            match item.resultado {
+                ResultadoItem::Aceito => resposta.aceitos += 1,
+                ResultadoItem::Duplicado => resposta.duplicados += 1,
+                ResultadoItem::Invalido => resposta.invalidos += 1,
+                ResultadoItem::Rejeitado => resposta.rejeitados += 1,
+            }
+        }
+        resposta.itens = itens;
+        resposta
+    }
+}
+
+// Separa o corpo em itens, cada um validado à parte: um item ruim não derruba o lote.
+// Array JSON ou NDJSON (uma linha por pagamento, linhas em branco ignoradas). Só um array
+// malformado como um todo é erro do lote.
+pub fn ler_lote(corpo: &[u8], ndjson: bool) -> Result<Vec<Result<Payment, String>>, String> {
+    let validar = |item: Result<Payment, serde_json::Error>| item.map_err(|e| e.to_string());
+
+    if ndjson {
+        return Ok(corpo
+            .split(|b| *b == b'\n')
+            .map(|linha| linha.trim_ascii())
+            .filter(|linha| !linha.is_empty())
+            .map(|linha| validar(serde_json::from_slice(linha)))
+            .collect());
+    }
+
+    let valores: Vec<serde_json::Value> =
+        serde_json::from_slice(corpo).map_err(|e| format!("array JSON inválido: {}", e))?;
+    Ok(valores
+        .into_iter()
+        .map(|valor| validar(serde_json::from_value(valor)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3";
+
+    #[test]
+    fn array_valida_cada_item() {
+        let corpo = format!(r#"[{{"correlationId": "{ID}", "amount": 19.9}}, {{"amount": 1}}, 3]"#);
+        let itens = ler_lote(corpo.as_bytes(), false).unwrap();
+        assert_eq!(itens.len(), 3);
+        assert_eq!(itens[0].as_ref().unwrap().amount.0, 1990);
+        assert!(itens[1].is_err());
+        assert!(itens[2].is_err());
+
+        assert!(ler_lote(b"[{", false).is_err());
+        assert!(ler_lote(b"[]", false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn ndjson_ignora_linhas_em_branco() {
+        let corpo = format!(
+            "{{\"correlationId\": \"{ID}\", \"amount\": 10}}\r\n\n  \n{{\"correlationId\": \"{ID}\", \"amount\": -1}}\n"
+        );
+        let itens = ler_lote(corpo.as_bytes(), true).unwrap();
+        assert_eq!(itens.len(), 2);
+        assert_eq!(itens[0].as_ref().unwrap().amount.0, 1000);
+        assert!(itens[1].is_err());
+    }
+
+    #[test]
+    fn resposta_conta_por_resultado() {
+        let item = |indice, resultado| ItemLote {
+            indice,
+            correlation_id: None,
+            resultado,
+            erro: None,
+            retry_after_secs: None,
+        };
+        let resposta = RespostaLote::new(vec![
+            item(0, ResultadoItem::Aceito),
+            item(1, ResultadoItem::Duplicado),
+            item(2, ResultadoItem::Aceito),
+            item(3, ResultadoItem::Invalido),
+        ]);
+        assert_eq!(
+            (
+                resposta.aceitos,
+                resposta.duplicados,
+                resposta.invalidos,
+                resposta.rejeitados
+            ),
+            (2, 1, 1, 0)
+        );
+    }
+}
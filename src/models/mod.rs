@@ -1,4 +1,10 @@
 pub mod data_range;
+pub mod dead_letter;
+pub mod dinheiro;
+pub mod expurgo;
+pub mod lote;
 pub mod payment;
 pub mod processor;
+pub mod prontidao;
+pub mod status;
 pub mod summary;
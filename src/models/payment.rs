@@ -3,37 +3,57 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::models::processor::TipoProcessador;
+use crate::models::dinheiro::Centavos;
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Payment {
     #[serde(rename = "correlationId")]
     pub correlation_id: Uuid,
-    pub amount: f64,
+    pub amount: Centavos,
     #[serde(rename = "requestedAt")]
     #[serde(default)]
     pub requested_at: Option<DateTime<Utc>>,
     #[serde(default)]
-    pub tipo: Option<TipoProcessador>,
+    pub tipo: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct PaymentRequest {
     #[serde(rename = "correlationId")]
     pub correlation_id: Uuid,
-    pub amount: f64,
+    pub amount: Centavos,
     #[serde(rename = "requestedAt")]
     #[serde(default)]
     pub requested_at: DateTime<Utc>,
 }
 
+// Formato gravado em `payment:{id}`: valor em centavos inteiros, que o Lua do sumário
+// soma sem passar por ponto flutuante.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct PagamentoArmazenado {
+    #[serde(rename = "correlationId")]
+    pub correlation_id: Uuid,
+    pub centavos: i64,
+    #[serde(rename = "requestedAt")]
+    pub requested_at: DateTime<Utc>,
+    pub tipo: String,
+}
+
 impl Payment {
     pub fn update_date(&mut self) {
         self.requested_at.get_or_insert_with(Utc::now);
     }
-    pub fn set_processador(&mut self, tipo: TipoProcessador) {
+    pub fn set_processador(&mut self, tipo: String) {
         self.tipo = Some(tipo);
     }
+    pub fn to_armazenado(&self) -> PagamentoArmazenado {
+        PagamentoArmazenado {
+            correlation_id: self.correlation_id,
+            centavos: self.amount.0,
+            requested_at: self.requested_at.unwrap(),
+            tipo: self.tipo.clone().unwrap_or_default(),
+        }
+    }
     pub fn to_payment_request(&self) -> PaymentRequest {
         PaymentRequest {
             correlation_id: self.correlation_id,
@@ -1,46 +1,409 @@
-use std::sync::Arc;
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
+use crate::{
+    circuit_breaker::{CircuitBreaker, ConfigCircuito},
+    configuracao::duracao_ms,
+    constantes,
+    estatisticas::{ConfigEstatisticas, EstatisticasProcessador},
+    intervencao::{Controle, Intervencao},
+    limite::LimiteConcorrencia,
+    roteamento,
+};
+
+// Corpo do `/payments/service-health` dos processadores, também repassado via NATS.
 #[derive(Deserialize, Serialize, Debug, Clone)]
-pub struct Processor {
+pub struct SaudeProcessador {
     pub failing: bool,
     #[serde(rename = "minResponseTime")]
     pub min_response_time: u64,
-    #[serde(default = "default_address")]
+}
+
+// O que a líder publica via NATS e grava no Redis a cada health check, com ou sem
+// resposta do processador.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SnapshotSaude {
+    #[serde(flatten)]
+    pub saude: SaudeProcessador,
+    #[serde(rename = "atualizadoEm")]
+    pub atualizado_em: DateTime<Utc>,
+    pub token: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PoliticaSaudeObsoleta {
+    // O seguidor consulta o processador por conta própria, sem publicar.
+    Consultar,
+    // Ignora o `failing` antigo e deixa só o disjuntor decidir.
+    Degradar,
+}
+
+impl FromStr for PoliticaSaudeObsoleta {
+    type Err = String;
+
+    fn from_str(valor: &str) -> Result<Self, Self::Err> {
+        match valor.to_ascii_lowercase().as_str() {
+            "consultar" => Ok(PoliticaSaudeObsoleta::Consultar),
+            "degradar" => Ok(PoliticaSaudeObsoleta::Degradar),
+            outra => Err(format!("política desconhecida: {}", outra)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigSaude {
+    // Pausa entre health checks, somada ao `minResponseTime` do processador.
+    #[serde(rename = "intervalo_ms", with = "duracao_ms")]
+    pub intervalo: Duration,
+    // Idade máxima da última saúde conhecida antes de a política entrar em ação.
+    #[serde(rename = "validade_ms", with = "duracao_ms")]
+    pub validade: Duration,
+    #[serde(rename = "obsoleta")]
+    pub politica: PoliticaSaudeObsoleta,
+}
+
+impl Default for ConfigSaude {
+    fn default() -> Self {
+        Self {
+            intervalo: Duration::from_millis(constantes::SAUDE_INTERVALO_MS),
+            validade: Duration::from_millis(constantes::SAUDE_VALIDADE_MS),
+            politica: PoliticaSaudeObsoleta::Consultar,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ConfigProcessador {
+    pub nome: String,
+    pub url: String,
+    // Menor valor = maior prioridade. Na estratégia `prioridade`, só o nível mais
+    // prioritário recebe tráfego antes do limiar de fallback.
+    #[serde(default)]
+    pub prioridade: u8,
+    // Fração cobrada por transação (0.05 = 5%).
+    #[serde(default)]
+    pub taxa: f64,
+    // Requisições simultâneas permitidas para este processador; 0 = sem limite.
+    #[serde(default)]
+    pub max_concorrencia: usize,
+    // Fatia do tráfego na estratégia `ponderada`.
+    #[serde(default = "peso_padrao")]
+    pub peso: u32,
+}
+
+fn peso_padrao() -> u32 {
+    1
+}
+
+#[derive(Debug)]
+pub struct Processor {
+    pub nome: String,
     pub address: String,
-    #[serde(default = "default_tipo")]
-    pub tipo: TipoProcessador,
+    pub prioridade: u8,
+    pub taxa: f64,
+    pub peso: u32,
+    pub failing: bool,
+    pub min_response_time: u64,
+    // Quando a saúde acima foi medida; `None` até a primeira chegar.
+    pub saude_em: Option<DateTime<Utc>>,
+    pub limite: Arc<LimiteConcorrencia>,
+    // `failing` reflete o health check; o disjuntor reflete as requisições reais.
+    pub disjuntor: CircuitBreaker,
+    // Desfechos das requisições reais, desta instância e das outras.
+    pub resultados: EstatisticasProcessador,
+    // Definida por um operador; tem precedência sobre `failing`.
+    pub intervencao: Controle,
+}
+
+#[derive(Serialize)]
+pub struct ResumoProcessador {
+    pub nome: String,
+    pub url: String,
+    pub prioridade: u8,
+    pub taxa: f64,
+    pub peso: u32,
+    pub failing: bool,
+    #[serde(rename = "minResponseTime")]
+    pub min_response_time: u64,
+    #[serde(rename = "saudeEm")]
+    pub saude_em: Option<DateTime<Utc>>,
+    #[serde(rename = "slotsLivres")]
+    pub slots_livres: usize,
+    pub circuito: &'static str,
+    // Visão consolidada das instâncias na janela de estatísticas.
+    pub amostras: u64,
+    #[serde(rename = "taxaErro")]
+    pub taxa_erro: f64,
+    pub timeouts: u64,
+    #[serde(rename = "latenciaP50Ms")]
+    pub latencia_p50_ms: Option<u64>,
+    #[serde(rename = "latenciaP99Ms")]
+    pub latencia_p99_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intervencao: Option<Intervencao>,
 }
 
 impl Processor {
+    pub fn resumo(&self, estatisticas: &ConfigEstatisticas) -> ResumoProcessador {
+        let resultados = self
+            .resultados
+            .consolidado(Instant::now(), estatisticas.validade_pares());
+        ResumoProcessador {
+            nome: self.nome.clone(),
+            url: self.address.clone(),
+            prioridade: self.prioridade,
+            taxa: self.taxa,
+            peso: self.peso,
+            failing: self.failing,
+            min_response_time: self.min_response_time,
+            saude_em: self.saude_em,
+            slots_livres: self.limite.disponiveis(),
+            circuito: self.disjuntor.estado().as_str(),
+            amostras: resultados.total,
+            taxa_erro: resultados.taxa_erro(),
+            timeouts: resultados.timeouts,
+            latencia_p50_ms: resultados.percentil(0.5),
+            latencia_p99_ms: resultados.percentil(0.99),
+            intervencao: self.intervencao.ativa(Utc::now()).cloned(),
+        }
+    }
+
+    // (latência em ms, taxa de erro). Com amostras suficientes no cluster, a latência real
+    // substitui a do health check.
+    pub fn desempenho(&self, agora: Instant, estatisticas: &ConfigEstatisticas) -> (u64, f64) {
+        let resultados = self
+            .resultados
+            .consolidado(agora, estatisticas.validade_pares());
+        if resultados.total < estatisticas.min_amostras {
+            return (self.min_response_time, 0.0);
+        }
+        (
+            resultados
+                .percentil(0.5)
+                .unwrap_or(self.min_response_time)
+                .max(self.min_response_time),
+            resultados.taxa_erro(),
+        )
+    }
+
+    // Aplica uma medição de saúde, a não ser que já exista outra mais recente.
+    pub fn aplicar_saude(&mut self, saude: &SaudeProcessador, em: DateTime<Utc>) -> bool {
+        if self.saude_em.is_some_and(|atual| atual > em) {
+            return false;
+        }
+        self.failing = saude.failing;
+        self.min_response_time = saude.min_response_time;
+        self.saude_em = Some(em);
+        true
+    }
+
+    // Sem nenhuma medição ainda, a idade conta a partir de `desde` (o boot).
+    pub fn saude_obsoleta(
+        &self,
+        agora: DateTime<Utc>,
+        desde: DateTime<Utc>,
+        validade: Duration,
+    ) -> bool {
+        let referencia = self.saude_em.unwrap_or(desde);
+        (agora - referencia)
+            .to_std()
+            .is_ok_and(|idade| idade > validade)
+    }
+
+    // Recarga de configuração: saúde, disjuntor e estatísticas continuam valendo.
+    pub fn reconfigurar(&mut self, config: &ConfigProcessador) {
+        self.address = config.url.clone();
+        self.prioridade = config.prioridade;
+        self.taxa = config.taxa;
+        self.peso = config.peso;
+        self.limite.ajustar(config.max_concorrencia);
+    }
+
     pub fn new_async(
-        failing: bool,
-        min_response_time: u64,
-        address: String,
-        tipo: TipoProcessador,
+        config: ConfigProcessador,
+        circuito: ConfigCircuito,
+        estatisticas: &ConfigEstatisticas,
     ) -> Arc<RwLock<Self>> {
         Arc::new(RwLock::new(Self {
-            failing,
-            min_response_time,
-            address,
-            tipo,
+            nome: config.nome,
+            address: config.url,
+            prioridade: config.prioridade,
+            taxa: config.taxa,
+            peso: config.peso,
+            failing: false,
+            min_response_time: 100,
+            saude_em: None,
+            limite: LimiteConcorrencia::new(config.max_concorrencia),
+            disjuntor: CircuitBreaker::new(circuito),
+            resultados: EstatisticasProcessador::new(estatisticas.janela_secs),
+            intervencao: Controle::default(),
         }))
     }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
-pub enum TipoProcessador {
-    Default,
-    Fallback,
-    None,
+// Par default/fallback usado quando a configuração não lista processadores.
+pub fn registro_padrao() -> Vec<ConfigProcessador> {
+    vec![
+        ConfigProcessador {
+            nome: "default".to_string(),
+            url: constantes::URL_DEFAULT.to_string(),
+            prioridade: 0,
+            taxa: 0.05,
+            max_concorrencia: 0,
+            peso: 1,
+        },
+        ConfigProcessador {
+            nome: "fallback".to_string(),
+            url: constantes::URL_FALLBACK.to_string(),
+            prioridade: 1,
+            taxa: 0.15,
+            max_concorrencia: 0,
+            peso: 1,
+        },
+    ]
 }
 
-fn default_tipo() -> TipoProcessador {
-    TipoProcessador::None
+pub fn validar_registro(registro: &[ConfigProcessador]) -> Result<(), String> {
+    if registro.is_empty() {
+        return Err("nenhum processador configurado".to_string());
+    }
+    for (i, config) in registro.iter().enumerate() {
+        // O nome vira parte do subject NATS e das chaves no Redis.
+        if config.nome.is_empty()
+            || !config
+                .nome
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("nome de processador inválido: {:?}", config.nome));
+        }
+        if registro[..i].iter().any(|outro| outro.nome == config.nome) {
+            return Err(format!("processador duplicado: {}", config.nome));
+        }
+    }
+    Ok(())
+}
+
+// Dentro do mesmo nível de prioridade, o mais barato vem primeiro.
+pub fn ordenar_registro(mut registro: Vec<ConfigProcessador>) -> Vec<ConfigProcessador> {
+    registro
+        .sort_by(|a, b| roteamento::preferencia((a.prioridade, a.taxa), (b.prioridade, b.taxa)));
+    registro
 }
 
-fn default_address() -> String {
-    String::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(nome: &str, prioridade: u8) -> ConfigProcessador {
+        ConfigProcessador {
+            nome: nome.to_string(),
+            url: "http://localhost:8001".to_string(),
+            prioridade,
+            taxa: 0.05,
+            max_concorrencia: 0,
+            peso: 1,
+        }
+    }
+
+    fn criar(config: ConfigProcessador) -> Processor {
+        Arc::try_unwrap(Processor::new_async(
+            config,
+            ConfigCircuito::default(),
+            &ConfigEstatisticas::default(),
+        ))
+        .unwrap()
+        .into_inner()
+    }
+
+    fn processador() -> Processor {
+        criar(config("default", 0))
+    }
+
+    fn saude(failing: bool) -> SaudeProcessador {
+        SaudeProcessador {
+            failing,
+            min_response_time: 42,
+        }
+    }
+
+    #[test]
+    fn medicao_antiga_nao_sobrescreve_a_nova() {
+        let mut processor = processador();
+        let t0 = DateTime::from_timestamp(1_000, 0).unwrap();
+        let t1 = DateTime::from_timestamp(1_005, 0).unwrap();
+
+        assert!(processor.aplicar_saude(&saude(true), t1));
+        assert!(!processor.aplicar_saude(&saude(false), t0));
+        assert!(processor.failing);
+        assert_eq!(processor.saude_em, Some(t1));
+
+        assert!(processor.aplicar_saude(&saude(false), t1));
+        assert!(!processor.failing);
+    }
+
+    #[test]
+    fn obsolescencia_conta_do_boot_ate_a_primeira_medicao() {
+        let mut processor = processador();
+        let validade = Duration::from_secs(15);
+        let boot = DateTime::from_timestamp(1_000, 0).unwrap();
+        let agora = DateTime::from_timestamp(1_010, 0).unwrap();
+
+        assert!(!processor.saude_obsoleta(agora, boot, validade));
+        assert!(processor.saude_obsoleta(agora + validade, boot, validade));
+
+        processor.aplicar_saude(&saude(false), agora);
+        assert!(!processor.saude_obsoleta(agora + validade, boot, validade));
+        assert!(processor.saude_obsoleta(
+            agora + validade + Duration::from_millis(1),
+            boot,
+            validade
+        ));
+        // Relógio de outra instância adiantado: medição "do futuro" não é obsoleta.
+        assert!(!processor.saude_obsoleta(boot, boot, validade));
+    }
+
+    #[test]
+    fn recarga_com_prioridades_trocadas_vale_sem_reordenar_o_registro() {
+        use crate::roteamento::{
+            CandidatoProcessador, ContextoRoteamento, PrioridadeEstrita, RoutingStrategy,
+        };
+
+        let registro = ordenar_registro(vec![config("fallback", 1), config("default", 0)]);
+        let mut processadores: Vec<_> = registro.into_iter().map(criar).collect();
+        assert_eq!(processadores[0].nome, "default");
+
+        for processor in processadores.iter_mut() {
+            let trocada = config(&processor.nome, 1 - processor.prioridade);
+            processor.reconfigurar(&trocada);
+        }
+        let candidatos: Vec<_> = processadores
+            .iter()
+            .map(|p| CandidatoProcessador {
+                prioridade: p.prioridade,
+                taxa: p.taxa,
+                latencia_ms: 100,
+                taxa_erro: 0.0,
+                peso: p.peso,
+                disponivel: true,
+            })
+            .collect();
+        let contexto = ContextoRoteamento {
+            tentativa: 0,
+            fallback_threshold: 30,
+            amount: 100.0,
+        };
+        // O registro segue na ordem do boot; a escolha segue a prioridade nova.
+        assert_eq!(processadores[1].nome, "fallback");
+        assert_eq!(PrioridadeEstrita.escolher(&candidatos, &contexto), Some(1));
+    }
 }
@@ -0,0 +1,152 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct VerificacaoRedis {
+    pub ok: bool,
+    #[serde(rename = "latenciaMs", skip_serializing_if = "Option::is_none")]
+    pub latencia_ms: Option<u64>,
+    #[serde(rename = "conexoesLivres")]
+    pub conexoes_livres: usize,
+    #[serde(rename = "conexoesMax")]
+    pub conexoes_max: usize,
+    // Tarefas esperando uma conexão do pool.
+    pub aguardando: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub erro: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VerificacaoNats {
+    pub ok: bool,
+    pub estado: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SaudeConhecida {
+    pub nome: String,
+    #[serde(rename = "saudeEm")]
+    pub saude_em: Option<DateTime<Utc>>,
+    pub obsoleta: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VerificacaoSaude {
+    pub ok: bool,
+    pub processadores: Vec<SaudeConhecida>,
+}
+
+impl VerificacaoSaude {
+    // Basta um processador com saúde recente: o roteamento segue com ele.
+    pub fn new(processadores: Vec<SaudeConhecida>) -> Self {
+        Self {
+            ok: processadores.iter().any(|p| !p.obsoleta),
+            processadores,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct VerificacaoFilas {
+    pub ok: bool,
+    // Só a fila em memória tem capacidade fixa; a durável cresce no Redis.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ocupadas: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capacidade: Option<usize>,
+    // Canais sem nenhuma vaga.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cheias: Option<usize>,
+}
+
+impl VerificacaoFilas {
+    // `(ocupadas, capacidade)` de cada canal de worker.
+    pub fn new(canais: &[(usize, usize)], ocupacao_maxima: f64) -> Self {
+        let ocupadas: usize = canais.iter().map(|(ocupadas, _)| ocupadas).sum();
+        let capacidade: usize = canais.iter().map(|(_, capacidade)| capacidade).sum();
+        let cheias = canais
+            .iter()
+            .filter(|(ocupadas, capacidade)| ocupadas >= capacidade)
+            .count();
+        Self {
+            ok: cheias < canais.len() && (ocupadas as f64) < capacidade as f64 * ocupacao_maxima,
+            ocupadas: Some(ocupadas),
+            capacidade: Some(capacidade),
+            cheias: Some(cheias),
+        }
+    }
+
+    pub fn duravel() -> Self {
+        Self {
+            ok: true,
+            ocupadas: None,
+            capacidade: None,
+            cheias: None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Prontidao {
+    pub pronto: bool,
+    pub encerrando: bool,
+    pub redis: VerificacaoRedis,
+    pub nats: VerificacaoNats,
+    pub saude: VerificacaoSaude,
+    pub filas: VerificacaoFilas,
+}
+
+impl Prontidao {
+    pub fn new(
+        encerrando: bool,
+        redis: VerificacaoRedis,
+        nats: VerificacaoNats,
+        saude: VerificacaoSaude,
+        filas: VerificacaoFilas,
+    ) -> Self {
+        Self {
+            pronto: !encerrando && redis.ok && nats.ok && saude.ok && filas.ok,
+            encerrando,
+            redis,
+            nats,
+            saude,
+            filas,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filas_saturadas_pela_ocupacao_ou_por_todas_cheias() {
+        assert!(VerificacaoFilas::new(&[(10, 100), (50, 100)], 0.9).ok);
+        assert!(!VerificacaoFilas::new(&[(90, 100), (90, 100)], 0.9).ok);
+
+        let todas_cheias = VerificacaoFilas::new(&[(1, 1), (1, 1)], 1.5);
+        assert!(!todas_cheias.ok);
+        assert_eq!(todas_cheias.cheias, Some(2));
+
+        let uma_cheia = VerificacaoFilas::new(&[(100, 100), (0, 100)], 0.9);
+        assert!(uma_cheia.ok);
+        assert_eq!(uma_cheia.cheias, Some(1));
+    }
+
+    #[test]
+    fn saude_basta_um_processador_recente() {
+        let conhecida = |nome: &str, obsoleta| SaudeConhecida {
+            nome: nome.to_string(),
+            saude_em: None,
+            obsoleta,
+        };
+        assert!(
+            VerificacaoSaude::new(vec![
+                conhecida("default", true),
+                conhecida("fallback", false)
+            ])
+            .ok
+        );
+        assert!(!VerificacaoSaude::new(vec![conhecida("default", true)]).ok);
+    }
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Recebido -> Despachado -> Confirmado | Falhou | DeadLetter.
+// Falhou indica que a última tentativa falhou; o worker volta a Despachado na próxima.
+// Desconhecido: o POST expirou sem resposta e o processador está sendo consultado antes
+// de qualquer reenvio.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EstadoPagamento {
+    Recebido,
+    Despachado,
+    Confirmado,
+    Falhou,
+    Desconhecido,
+    DeadLetter,
+}
+
+impl EstadoPagamento {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EstadoPagamento::Recebido => "Recebido",
+            EstadoPagamento::Despachado => "Despachado",
+            EstadoPagamento::Confirmado => "Confirmado",
+            EstadoPagamento::Falhou => "Falhou",
+            EstadoPagamento::Desconhecido => "Desconhecido",
+            EstadoPagamento::DeadLetter => "DeadLetter",
+        }
+    }
+
+    pub fn from_nome(nome: &str) -> Option<Self> {
+        match nome {
+            "Recebido" => Some(EstadoPagamento::Recebido),
+            "Despachado" => Some(EstadoPagamento::Despachado),
+            "Confirmado" => Some(EstadoPagamento::Confirmado),
+            "Falhou" => Some(EstadoPagamento::Falhou),
+            "Desconhecido" => Some(EstadoPagamento::Desconhecido),
+            "DeadLetter" => Some(EstadoPagamento::DeadLetter),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct StatusPagamento {
+    #[serde(rename = "correlationId")]
+    pub correlation_id: Uuid,
+    pub estado: EstadoPagamento,
+    pub tipo: Option<String>,
+    #[serde(rename = "requestedAt")]
+    pub requested_at: Option<DateTime<Utc>>,
+    pub tentativas: u32,
+    #[serde(rename = "ultimoErro")]
+    pub ultimo_erro: Option<String>,
+}
@@ -1,7 +1,9 @@
+use std::collections::BTreeMap;
+
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Default, Debug, PartialEq)]
 pub struct Summary {
     #[serde(rename = "totalRequests")]
     pub total_requests: u64,
@@ -9,8 +11,16 @@ pub struct Summary {
     pub total_amount: Decimal,
 }
 
-#[derive(Deserialize, Serialize)]
+// Uma chave por processador do registro, ex.: `{"default": {...}, "fallback": {...}}`.
+#[derive(Deserialize, Serialize, Default, Debug, PartialEq)]
 pub struct PaymentSummary {
-    pub default: Summary,
-    pub fallback: Summary,
+    #[serde(flatten)]
+    pub processadores: BTreeMap<String, Summary>,
+}
+
+#[derive(Serialize)]
+pub struct RelatorioConsistencia {
+    pub consistente: bool,
+    pub agregado: PaymentSummary,
+    pub varredura: PaymentSummary,
 }
@@ -0,0 +1,178 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{
+    appstate::AppState,
+    configuracao::{ConfigPagamentos, ConfigRecarregavel, Configuracao},
+    constantes,
+    limite::LimiteConcorrencia,
+    roteamento::{self, RoutingStrategy},
+};
+
+// O que os workers leem a cada pagamento. Trocado inteiro numa recarga, então um
+// pagamento nunca mistura valores de duas configurações.
+pub struct ValoresDinamicos {
+    pub pagamentos: ConfigPagamentos,
+    pub roteamento: Arc<dyn RoutingStrategy>,
+}
+
+pub struct Recarga {
+    valores: RwLock<Arc<ValoresDinamicos>>,
+    // Configuração efetiva e o horário da última recarga aplicada. O lock também impede
+    // duas recargas ao mesmo tempo.
+    atual: Mutex<(Configuracao, DateTime<Utc>)>,
+    // Relido no SIGHUP.
+    arquivo: Option<PathBuf>,
+    pub limite_prioritario: Arc<LimiteConcorrencia>,
+    pub limite_pagamentos: Arc<LimiteConcorrencia>,
+}
+
+// Publicada no NATS por quem recebeu a recarga (SIGHUP ou `PUT /admin/config`).
+#[derive(Serialize, Deserialize)]
+pub struct MensagemRecarga {
+    pub origem: String,
+    #[serde(rename = "emitidaEm")]
+    pub emitida_em: DateTime<Utc>,
+    pub config: ConfigRecarregavel,
+}
+
+#[derive(Serialize)]
+pub struct ResultadoRecarga {
+    #[serde(rename = "emitidaEm")]
+    pub emitida_em: DateTime<Utc>,
+    // Seções alteradas que só valem depois de reiniciar.
+    pub pendentes: Vec<String>,
+}
+
+fn criar_valores(config: &Configuracao) -> Result<ValoresDinamicos, String> {
+    Ok(ValoresDinamicos {
+        pagamentos: config.pagamentos,
+        roteamento: roteamento::criar_estrategia(
+            &config.roteamento.estrategia,
+            config.roteamento.penalidade_latencia,
+        )?,
+    })
+}
+
+impl Recarga {
+    pub fn new(config: Configuracao, arquivo: Option<PathBuf>) -> Result<Self, String> {
+        Ok(Self {
+            valores: RwLock::new(Arc::new(criar_valores(&config)?)),
+            limite_prioritario: LimiteConcorrencia::new(config.servidor.prioritario.concorrencia),
+            limite_pagamentos: LimiteConcorrencia::new(config.servidor.pagamentos.concorrencia),
+            atual: Mutex::new((config, DateTime::<Utc>::MIN_UTC)),
+            arquivo,
+        })
+    }
+
+    pub fn valores(&self) -> Arc<ValoresDinamicos> {
+        self.valores.read().unwrap().clone()
+    }
+
+    pub async fn config_atual(&self) -> Configuracao {
+        self.atual.lock().await.0.clone()
+    }
+
+    pub fn reler_arquivo(&self) -> Result<Configuracao, String> {
+        Configuracao::carregar(self.arquivo.as_deref())
+    }
+}
+
+// Valida `nova` e aplica a parte recarregável. Recargas emitidas antes da última aplicada
+// são descartadas: com duas recargas quase simultâneas, as instâncias convergem para a
+// mais recente.
+pub async fn aplicar(
+    state: &AppState,
+    nova: Configuracao,
+    emitida_em: DateTime<Utc>,
+) -> Result<Vec<String>, String> {
+    let mut atual = state.recarga.atual.lock().await;
+    if emitida_em < atual.1 {
+        return Err(format!(
+            "recarga de {} é anterior à última aplicada ({})",
+            emitida_em, atual.1
+        ));
+    }
+    let mut erros = nova.validar();
+    // A fila só muda ao reiniciar: até lá, os pagamentos rodam com a que está em uso.
+    let mut em_uso = nova.clone();
+    em_uso.fila = atual.0.fila;
+    let erros_em_uso: Vec<_> = em_uso
+        .validar()
+        .into_iter()
+        .filter(|e| !erros.contains(e))
+        .collect();
+    erros.extend(erros_em_uso);
+    if !erros.is_empty() {
+        return Err(erros.join("\n"));
+    }
+    let mut nomes_atuais: Vec<_> = atual.0.processadores.iter().map(|p| &p.nome).collect();
+    let mut nomes_novos: Vec<_> = nova.processadores.iter().map(|p| &p.nome).collect();
+    nomes_atuais.sort();
+    nomes_novos.sort();
+    if nomes_atuais != nomes_novos {
+        return Err("incluir ou remover processadores exige reiniciar".to_string());
+    }
+    let valores = criar_valores(&nova)?;
+
+    for processor in state.processors.iter() {
+        let mut guard = processor.write().await;
+        if let Some(config) = nova.processadores.iter().find(|p| p.nome == guard.nome) {
+            guard.reconfigurar(config);
+        }
+    }
+    let recarregavel = nova.recarregavel();
+    state
+        .recarga
+        .limite_prioritario
+        .ajustar(recarregavel.concorrencia_prioritario);
+    state
+        .recarga
+        .limite_pagamentos
+        .ajustar(recarregavel.concorrencia_pagamentos);
+    *state.recarga.valores.write().unwrap() = Arc::new(valores);
+
+    // Só a parte recarregável entra na configuração guardada; o resto (inclusive o token
+    // de admin, que sai oculto ao serializar) continua sendo o do boot.
+    let pendentes = atual.0.pendentes_de_reinicio(&nova);
+    atual.0.aplicar_recarregavel(&recarregavel);
+    atual.1 = emitida_em;
+    Ok(pendentes)
+}
+
+// Aplica aqui e repassa às outras instâncias.
+pub async fn recarregar(state: &AppState, nova: Configuracao) -> Result<ResultadoRecarga, String> {
+    let emitida_em = Utc::now();
+    let pendentes = aplicar(state, nova.clone(), emitida_em).await?;
+    info!(pendentes = ?pendentes, "configuração recarregada");
+    if !pendentes.is_empty() {
+        warn!(pendentes = ?pendentes, "seções alteradas só valem depois de reiniciar");
+    }
+
+    let mensagem = MensagemRecarga {
+        origem: state.lideranca.identidade.clone(),
+        emitida_em,
+        config: nova.recarregavel(),
+    };
+    if let Err(e) = state
+        .nats_client
+        .publish(
+            constantes::RECARGA_SUBJECT,
+            serde_json::to_vec(&mensagem).unwrap().into(),
+        )
+        .await
+    {
+        warn!(erro = %e, "falha ao repassar a recarga via NATS");
+    }
+    Ok(ResultadoRecarga {
+        emitida_em,
+        pendentes,
+    })
+}
@@ -0,0 +1,282 @@
+use std::{
+    cmp,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+// Fotografia de um processador no momento da decisão; a estratégia não toca em locks.
+#[derive(Debug, Clone)]
+pub struct CandidatoProcessador {
+    pub prioridade: u8,
+    pub taxa: f64,
+    pub latencia_ms: u64,
+    // Fração das requisições recentes que falharam, somando todas as instâncias.
+    pub taxa_erro: f64,
+    pub peso: u32,
+    pub disponivel: bool,
+}
+
+// Ordem de preferência entre processadores, `(prioridade, taxa)`: o nível mais
+// prioritário e, dentro dele, o mais barato. Sempre com os valores atuais; o registro fica
+// na ordem do boot mesmo que uma recarga troque as prioridades.
+pub fn preferencia(a: (u8, f64), b: (u8, f64)) -> cmp::Ordering {
+    a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ContextoRoteamento {
+    pub tentativa: u8,
+    pub fallback_threshold: u8,
+    pub amount: f64,
+}
+
+pub trait RoutingStrategy: Send + Sync {
+    // Índice em `candidatos` (mesma ordem de `AppState::processors`), ou `None` para
+    // esperar e tentar de novo.
+    fn escolher(
+        &self,
+        candidatos: &[CandidatoProcessador],
+        contexto: &ContextoRoteamento,
+    ) -> Option<usize>;
+}
+
+// Comportamento original: o nível mais prioritário sempre que disponível; os demais
+// só depois de `fallback_threshold` tentativas.
+pub struct PrioridadeEstrita;
+
+impl RoutingStrategy for PrioridadeEstrita {
+    fn escolher(
+        &self,
+        candidatos: &[CandidatoProcessador],
+        contexto: &ContextoRoteamento,
+    ) -> Option<usize> {
+        let prioridade_principal = candidatos.iter().map(|c| c.prioridade).min()?;
+        let libera_secundarios = contexto.tentativa >= contexto.fallback_threshold;
+
+        candidatos
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.disponivel)
+            .filter(|(_, c)| c.prioridade == prioridade_principal || libera_secundarios)
+            .min_by(|(_, a), (_, b)| preferencia((a.prioridade, a.taxa), (b.prioridade, b.taxa)))
+            .map(|(i, _)| i)
+    }
+}
+
+// Custo esperado = amount * (taxa + latência * penalidade / (1 - taxa de erro)). A
+// penalidade é a fração do valor que cada milissegundo "custa"; 0.0001 equivale a 1% a
+// cada 100ms. A taxa só é cobrada no sucesso, mas cada falha gasta mais uma latência.
+pub struct MenorCusto {
+    pub penalidade_latencia: f64,
+}
+
+impl MenorCusto {
+    fn custo(&self, candidato: &CandidatoProcessador, amount: f64) -> f64 {
+        let tentativas_esperadas = 1.0 / (1.0 - candidato.taxa_erro).max(0.01);
+        amount
+            * (candidato.taxa
+                + candidato.latencia_ms as f64 * self.penalidade_latencia * tentativas_esperadas)
+    }
+}
+
+impl RoutingStrategy for MenorCusto {
+    fn escolher(
+        &self,
+        candidatos: &[CandidatoProcessador],
+        contexto: &ContextoRoteamento,
+    ) -> Option<usize> {
+        candidatos
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.disponivel)
+            .min_by(|(_, a), (_, b)| {
+                self.custo(a, contexto.amount)
+                    .total_cmp(&self.custo(b, contexto.amount))
+            })
+            .map(|(i, _)| i)
+    }
+}
+
+// Distribui entre os disponíveis na proporção de `peso`. Usa um contador em vez de
+// sorteio, então a divisão é exata a cada ciclo de `soma dos pesos` requisições.
+#[derive(Default)]
+pub struct DivisaoPonderada {
+    contador: AtomicU64,
+}
+
+impl RoutingStrategy for DivisaoPonderada {
+    fn escolher(
+        &self,
+        candidatos: &[CandidatoProcessador],
+        _contexto: &ContextoRoteamento,
+    ) -> Option<usize> {
+        let total: u64 = candidatos
+            .iter()
+            .filter(|c| c.disponivel)
+            .map(|c| c.peso as u64)
+            .sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut posicao = self.contador.fetch_add(1, Ordering::Relaxed) % total;
+        for (i, candidato) in candidatos.iter().enumerate() {
+            if !candidato.disponivel {
+                continue;
+            }
+            if posicao < candidato.peso as u64 {
+                return Some(i);
+            }
+            posicao -= candidato.peso as u64;
+        }
+        None
+    }
+}
+
+pub fn criar_estrategia(
+    nome: &str,
+    penalidade_latencia: f64,
+) -> Result<Arc<dyn RoutingStrategy>, String> {
+    match nome.to_ascii_lowercase().as_str() {
+        "prioridade" => Ok(Arc::new(PrioridadeEstrita)),
+        "menor_custo" => Ok(Arc::new(MenorCusto {
+            penalidade_latencia,
+        })),
+        "ponderada" => Ok(Arc::new(DivisaoPonderada::default())),
+        outro => Err(format!("estratégia de roteamento desconhecida: {}", outro)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidato(
+        prioridade: u8,
+        taxa: f64,
+        latencia_ms: u64,
+        disponivel: bool,
+    ) -> CandidatoProcessador {
+        CandidatoProcessador {
+            prioridade,
+            taxa,
+            latencia_ms,
+            taxa_erro: 0.0,
+            peso: 1,
+            disponivel,
+        }
+    }
+
+    fn contexto(tentativa: u8) -> ContextoRoteamento {
+        ContextoRoteamento {
+            tentativa,
+            fallback_threshold: 30,
+            amount: 100.0,
+        }
+    }
+
+    #[test]
+    fn prioridade_usa_principal_quando_saudavel() {
+        let candidatos = [candidato(0, 0.05, 100, true), candidato(1, 0.15, 10, true)];
+        assert_eq!(
+            PrioridadeEstrita.escolher(&candidatos, &contexto(0)),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn prioridade_espera_limiar_antes_do_fallback() {
+        let candidatos = [candidato(0, 0.05, 100, false), candidato(1, 0.15, 10, true)];
+        assert_eq!(PrioridadeEstrita.escolher(&candidatos, &contexto(29)), None);
+        assert_eq!(
+            PrioridadeEstrita.escolher(&candidatos, &contexto(30)),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn prioridade_sem_nenhum_disponivel() {
+        let candidatos = [
+            candidato(0, 0.05, 100, false),
+            candidato(1, 0.15, 10, false),
+        ];
+        assert_eq!(PrioridadeEstrita.escolher(&candidatos, &contexto(40)), None);
+    }
+
+    #[test]
+    fn menor_custo_prefere_taxa_menor_com_latencia_parecida() {
+        let estrategia = MenorCusto {
+            penalidade_latencia: 0.0001,
+        };
+        let candidatos = [candidato(0, 0.05, 120, true), candidato(1, 0.15, 100, true)];
+        assert_eq!(estrategia.escolher(&candidatos, &contexto(0)), Some(0));
+    }
+
+    #[test]
+    fn menor_custo_troca_quando_latencia_domina() {
+        let estrategia = MenorCusto {
+            penalidade_latencia: 0.0001,
+        };
+        // default: 5% + 2000ms * 0.01%/ms = 25%; fallback: 15% + 10ms = 15.1%.
+        let candidatos = [candidato(0, 0.05, 2000, true), candidato(1, 0.15, 10, true)];
+        assert_eq!(estrategia.escolher(&candidatos, &contexto(0)), Some(1));
+    }
+
+    #[test]
+    fn menor_custo_pesa_taxa_de_erro_do_cluster() {
+        let estrategia = MenorCusto {
+            penalidade_latencia: 0.0001,
+        };
+        // default: 5% + 600ms = 11%; fallback: 15% + 100ms = 16%.
+        let mut candidatos = [candidato(0, 0.05, 600, true), candidato(1, 0.15, 100, true)];
+        assert_eq!(estrategia.escolher(&candidatos, &contexto(0)), Some(0));
+
+        // Metade das chamadas ao default falhando dobra a latência esperada: 17%.
+        candidatos[0].taxa_erro = 0.5;
+        assert_eq!(estrategia.escolher(&candidatos, &contexto(0)), Some(1));
+    }
+
+    #[test]
+    fn menor_custo_ignora_indisponiveis() {
+        let estrategia = MenorCusto {
+            penalidade_latencia: 0.0001,
+        };
+        let candidatos = [candidato(0, 0.05, 10, false), candidato(1, 0.15, 10, true)];
+        assert_eq!(estrategia.escolher(&candidatos, &contexto(0)), Some(1));
+    }
+
+    #[test]
+    fn ponderada_respeita_proporcao() {
+        let estrategia = DivisaoPonderada::default();
+        let mut candidatos = [candidato(0, 0.05, 10, true), candidato(1, 0.15, 10, true)];
+        candidatos[0].peso = 3;
+        candidatos[1].peso = 1;
+
+        let mut contagem = [0usize; 2];
+        for _ in 0..400 {
+            contagem[estrategia.escolher(&candidatos, &contexto(0)).unwrap()] += 1;
+        }
+        assert_eq!(contagem, [300, 100]);
+    }
+
+    #[test]
+    fn ponderada_redistribui_quando_um_cai() {
+        let estrategia = DivisaoPonderada::default();
+        let mut candidatos = [candidato(0, 0.05, 10, false), candidato(1, 0.15, 10, true)];
+        candidatos[0].peso = 3;
+
+        for _ in 0..10 {
+            assert_eq!(estrategia.escolher(&candidatos, &contexto(0)), Some(1));
+        }
+    }
+
+    #[test]
+    fn criar_estrategia_rejeita_nome_desconhecido() {
+        assert!(criar_estrategia("prioridade", 0.0).is_ok());
+        assert!(criar_estrategia("menor_custo", 0.0001).is_ok());
+        assert!(criar_estrategia("ponderada", 0.0).is_ok());
+        assert!(criar_estrategia("aleatoria", 0.0).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(feature = "otlp")]
+use std::env;
+
+use tracing_subscriber::{EnvFilter, Layer, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::configuracao::{ConfigLog, FormatoLog};
+
+// Mantém o exportador OTLP vivo; `encerrar` envia o que ainda estiver no lote.
+pub struct Telemetria {
+    #[cfg(feature = "otlp")]
+    provedor: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+// O filtro já foi validado junto com o resto da configuração.
+pub fn iniciar(config: &ConfigLog) -> Telemetria {
+    let filtro = EnvFilter::new(&config.filtro);
+    let saida = match config.formato {
+        FormatoLog::Json => fmt::layer().json().with_span_list(true).boxed(),
+        FormatoLog::Texto => fmt::layer().boxed(),
+    };
+    let registro = tracing_subscriber::registry().with(filtro).with(saida);
+
+    #[cfg(feature = "otlp")]
+    {
+        let provedor = criar_provedor_otlp();
+        let camada = provedor.as_ref().map(|provedor| {
+            use opentelemetry::trace::TracerProvider;
+            tracing_opentelemetry::layer().with_tracer(provedor.tracer("rust-backend"))
+        });
+        registro.with(camada).init();
+        Telemetria { provedor }
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        registro.init();
+        Telemetria {}
+    }
+}
+
+// Só liga com `OTEL_EXPORTER_OTLP_ENDPOINT` definida (ex.: `http://localhost:4317`).
+#[cfg(feature = "otlp")]
+fn criar_provedor_otlp() -> Option<opentelemetry_sdk::trace::SdkTracerProvider> {
+    env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok()?;
+
+    let exportador = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .build()
+        .unwrap_or_else(|e| panic!("❌ Exportador OTLP inválido: {}", e));
+    let servico = env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "rust-backend".to_string());
+
+    Some(
+        opentelemetry_sdk::trace::SdkTracerProvider::builder()
+            .with_batch_exporter(exportador)
+            .with_resource(
+                opentelemetry_sdk::Resource::builder()
+                    .with_service_name(servico)
+                    .build(),
+            )
+            .build(),
+    )
+}
+
+impl Telemetria {
+    pub fn encerrar(self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provedor) = self.provedor {
+            let _ = provedor.shutdown();
+        }
+    }
+}
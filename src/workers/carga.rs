@@ -0,0 +1,17 @@
+use std::time::Duration;
+
+use crate::{admissao, appstate::AppState, constantes};
+
+// Mantém a medida de carga que a admissão consulta: medir a cada POST custaria um XLEN e
+// uma leitura de cada processador por requisição.
+pub async fn cria_worker_mede_carga(state: AppState) {
+    let mut intervalo =
+        tokio::time::interval(Duration::from_millis(constantes::ADMISSAO_MEDICAO_MS));
+    intervalo.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        intervalo.tick().await;
+        let carga = admissao::medir_carga(&state).await;
+        *state.carga.lock().unwrap() = carga;
+    }
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::{debug, warn};
+
+use crate::appstate::AppState;
+
+// Tira do armazenamento o que passou do horizonte de retenção. Roda em todas as
+// instâncias: remover o que já foi removido não tem efeito.
+pub async fn cria_worker_compactacao(state: AppState) {
+    let intervalo = Duration::from_secs(state.retencao.intervalo_compactacao_secs);
+
+    loop {
+        tokio::time::sleep(intervalo).await;
+
+        let horizonte = state
+            .retencao
+            .horizonte_micros(Utc::now().timestamp_micros() as u64);
+        let mut removidos = 0;
+        loop {
+            match state.armazenamento.compactar(horizonte).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    removidos += n;
+                    tokio::task::yield_now().await
+                }
+                Err(e) => {
+                    warn!(erro = %e, "falha na compactação");
+                    break;
+                }
+            }
+        }
+        debug!(removidos, horizonte, "compactação concluída");
+    }
+}
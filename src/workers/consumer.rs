@@ -1,21 +1,56 @@
 use axum::body::Bytes;
-use std::{sync::Arc, time::Duration};
+use chrono::Utc;
+use reqwest::StatusCode;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio::sync::{RwLock, mpsc::Receiver};
+use tracing::{Instrument, debug, error, info_span, warn};
 
 use crate::{
-    api::redis::salvar_pagamento,
-    appstate::AppState,
-    models::{
-        payment::Payment,
-        processor::{Processor, TipoProcessador},
+    api::{
+        fila::{self, BackendFila},
+        http::{ConsultaPagamento, consultar_pagamento_processador},
+        redis::{
+            guardar_sobra, registrar_desconhecido, registrar_falha, registrar_tentativa,
+            salvar_dead_letter,
+        },
     },
+    appstate::AppState,
+    armazenamento::salvar_pagamento,
+    constantes,
+    intervencao::{self, Acao},
+    limite::Permissao,
+    models::{dead_letter::DeadLetter, payment::Payment, processor::Processor},
+    roteamento::{CandidatoProcessador, ContextoRoteamento, RoutingStrategy},
 };
 
+// No encerramento o canal é fechado para envios e o worker segue até esvaziá-lo.
 pub async fn worker_processa_pagamento(state: AppState, mut receiver: Receiver<Bytes>) {
-    while let Some(body_bytes) = receiver.recv().await {
+    let mut fechado = false;
+    loop {
+        let body_bytes = tokio::select! {
+            biased;
+            body = receiver.recv() => match body {
+                Some(body) => body,
+                None => break,
+            },
+            _ = state.encerramento.aguardar_inicio(), if !fechado => {
+                receiver.close();
+                fechado = true;
+                continue;
+            }
+        };
         let payload: Payment = match simd_json::from_slice(&mut body_bytes.to_vec()) {
             Ok(p) => p,
-            Err(_) => {
+            Err(e) => {
+                warn!(erro = %e, "corpo inválido no canal de workers");
+                state
+                    .metricas
+                    .descartados
+                    .with_label_values(&["corpo_invalido"])
+                    .inc();
                 continue;
             }
         };
@@ -27,49 +62,283 @@ pub async fn worker_processa_pagamento(state: AppState, mut receiver: Receiver<B
             tipo: None,
         };
 
-        processa_pagamento(state.clone(), payment).await;
+        processa_pagamento(state.clone(), payment, None).await;
     }
 }
-async fn escolher_processador(
+
+pub async fn worker_processa_fila_duravel(state: AppState, consumidor: String) {
+    // Uma entrada por vez: o que viesse junto ficaria parado na PEL enquanto a primeira é
+    // tentada, e outro consumidor poderia reivindicá-lo.
+    let lote = 1;
+    let erro_delay = Duration::from_millis(500);
+    let intervalo_varredura = Duration::from_millis(state.fila.ocioso_ms);
+    let mut ultima_varredura: Option<Instant> = None;
+
+    // No encerramento para de ler; o que já foi lido e não terminou fica pendente no grupo.
+    while !state.encerramento.encerrando() {
+        // No boot e depois a cada `ocioso_ms`: o que ficou pendente para este consumidor e
+        // o que outros consumidores (que talvez nunca voltem) deixaram parado.
+        if ultima_varredura.is_none_or(|t| t.elapsed() >= intervalo_varredura) {
+            recupera_pendentes(&state, &consumidor, lote).await;
+            ultima_varredura = Some(Instant::now());
+        }
+        match fila::ler_fila(&state, &consumidor, ">", lote, 1000).await {
+            Ok(entradas) => {
+                processa_entradas(&state, &consumidor, entradas).await;
+            }
+            Err(e) => {
+                warn!(erro = %e, consumidor, "falha ao ler a fila");
+                tokio::time::sleep(erro_delay).await
+            }
+        }
+    }
+}
+
+async fn recupera_pendentes(state: &AppState, consumidor: &str, lote: usize) {
+    while !state.encerramento.encerrando() {
+        match fila::ler_fila(state, consumidor, "0", lote, 0).await {
+            Ok(entradas) if entradas.is_empty() => break,
+            Ok(entradas) => {
+                let total = entradas.len();
+                let confirmadas = processa_entradas(state, consumidor, entradas).await;
+                // Entradas que não puderam ser concluídas continuam pendentes; não insiste
+                // nelas até a próxima varredura.
+                if confirmadas < total {
+                    break;
+                }
+            }
+            Err(e) => {
+                warn!(erro = %e, consumidor, "falha ao ler pendências da fila");
+                break;
+            }
+        }
+    }
+
+    let mut cursor = "0-0".to_string();
+    while !state.encerramento.encerrando() {
+        match fila::reivindicar_orfaos(state, consumidor, state.fila.ocioso_ms, &cursor, lote).await
+        {
+            Ok((proximo, entradas)) => {
+                processa_entradas(state, consumidor, entradas).await;
+                if proximo == "0-0" {
+                    break;
+                }
+                cursor = proximo;
+            }
+            Err(e) => {
+                warn!(erro = %e, consumidor, "falha ao reivindicar entradas órfãs");
+                break;
+            }
+        }
+    }
+}
+
+async fn processa_entradas(
     state: &AppState,
-    retry: u8,
-    fallback_threshold: u8,
-) -> (Option<Arc<RwLock<Processor>>>, TipoProcessador) {
-    let is_default_failing = state.processors[0].read().await.failing;
-    if !is_default_failing {
-        return (Some(state.processors[0].clone()), TipoProcessador::Default);
+    consumidor: &str,
+    entradas: Vec<fila::EntradaFila>,
+) -> usize {
+    let mut confirmadas = 0;
+    for mut entrada in entradas {
+        let concluido = match simd_json::from_slice::<Payment>(&mut entrada.body) {
+            Ok(payload) => {
+                let payment = Payment {
+                    correlation_id: payload.correlation_id,
+                    amount: payload.amount,
+                    requested_at: None,
+                    tipo: None,
+                };
+                let posse = fila::Posse {
+                    consumidor,
+                    id: &entrada.id,
+                };
+                processa_pagamento(state.clone(), payment, Some(posse)).await
+            }
+            // Corpo inválido nunca vai ser processado; confirma para não voltar em todo boot.
+            Err(e) => {
+                warn!(erro = %e, entrada = entrada.id, "corpo inválido na fila");
+                state
+                    .metricas
+                    .descartados
+                    .with_label_values(&["corpo_invalido"])
+                    .inc();
+                true
+            }
+        };
+
+        if concluido && fila::confirmar_entrada(state, &entrada.id).await.is_ok() {
+            confirmadas += 1;
+        }
     }
+    confirmadas
+}
 
-    if retry >= fallback_threshold {
-        let is_fallback_failing = state.processors[1].read().await.failing;
-        if !is_fallback_failing {
-            return (Some(state.processors[1].clone()), TipoProcessador::Fallback);
+async fn escolher_processador(
+    state: &AppState,
+    roteamento: &dyn RoutingStrategy,
+    contexto: &ContextoRoteamento,
+) -> Option<(Arc<RwLock<Processor>>, String, Permissao, bool)> {
+    let agora = Instant::now();
+    let agora_utc = Utc::now();
+    let mut candidatos = Vec::with_capacity(state.processors.len());
+    // Posição em `state.processors` de cada candidato; drenados nem entram na lista.
+    let mut indices = Vec::with_capacity(state.processors.len());
+    let mut forcado = None;
+    // Nível mais prioritário entre todos os processadores, drenados inclusive. Vem dos
+    // valores atuais: uma recarga pode ter trocado as prioridades, e o registro continua na
+    // ordem do boot.
+    let mut principal = u8::MAX;
+    for (i, processor_arc) in state.processors.iter().enumerate() {
+        let guard = processor_arc.read().await;
+        principal = principal.min(guard.prioridade);
+        let acao = guard.intervencao.ativa(agora_utc).map(|i| i.acao);
+        if acao == Some(Acao::Drenar) {
+            continue;
         }
+        if acao == Some(Acao::Forcar) {
+            forcado = Some(candidatos.len());
+        }
+        let (latencia_ms, taxa_erro) = guard.desempenho(agora, &state.estatisticas);
+        candidatos.push(CandidatoProcessador {
+            prioridade: guard.prioridade,
+            taxa: guard.taxa,
+            latencia_ms,
+            taxa_erro,
+            peso: guard.peso,
+            disponivel: (!guard.failing || matches!(acao, Some(Acao::Forcar | Acao::Saudavel)))
+                && guard.disjuntor.disponivel(agora)
+                && guard.limite.disponiveis() > 0,
+        });
+        indices.push(i);
+    }
+    // Forçado: é o único candidato, e a estratégia não espera o limiar de fallback.
+    if let Some(forcado) = forcado {
+        candidatos = vec![candidatos.swap_remove(forcado)];
+        indices = vec![indices[forcado]];
     }
 
-    (None, TipoProcessador::None)
+    let indice = roteamento.escolher(&candidatos, contexto)?;
+    let processor_arc = state.processors[indices[indice]].clone();
+    let mut guard = processor_arc.write().await;
+    // Outro worker pode ter ocupado o último slot (ou teste do meio-aberto) entre a
+    // fotografia e aqui.
+    let permit = guard.limite.tentar()?;
+    if !guard.disjuntor.permitir(agora) {
+        return None;
+    }
+    let nome = guard.nome.clone();
+    drop(guard);
+
+    let fallback = candidatos[indice].prioridade > principal;
+    if fallback {
+        debug!(
+            processador = %nome,
+            tentativa = contexto.tentativa,
+            "fallback para processador fora do nível principal"
+        );
+    }
+
+    Some((processor_arc, nome, permit, fallback))
+}
+
+enum Desfecho {
+    Sucesso,
+    Falha(String),
+    // O processador pode ou não ter cobrado: timeout depois de enviar, conexão caída no
+    // meio da resposta, ou 409/422 indicando que o correlationId já existe lá.
+    Ambiguo(String),
 }
 
-pub async fn processa_pagamento(state: AppState, mut payment: Payment) {
+enum Verificacao {
+    Cobrado,
+    NaoCobrado,
+    Esgotado,
+}
+
+#[tracing::instrument(
+    name = "processamento",
+    skip_all,
+    fields(correlation_id = %payment.correlation_id)
+)]
+pub async fn processa_pagamento(
+    state: AppState,
+    mut payment: Payment,
+    posse: Option<fila::Posse<'_>>,
+) -> bool {
     let mut retry_delay = Duration::from_millis(50);
-    let max_retry_delay = Duration::from_secs(1);
-    let max_retry_times = 40u8;
+    let max_retry_delay = Duration::from_millis(constantes::ESPERA_MAX_TENTATIVA_MS);
+    // Uma fotografia por pagamento: uma recarga no meio vale para o próximo.
+    let valores = state.recarga.valores();
+    let max_retry_times = valores.pagamentos.tentativas_maximas;
     let mut retry_times = 0u8;
     let fallback_threshold =
-        (max_retry_times as f32 * (state.retry_default_percentage / 100.0)).floor() as u8;
+        (max_retry_times as f32 * (valores.pagamentos.percentual_fallback / 100.0)).floor() as u8;
+    let mut ultimo_erro = String::from("nenhum processador disponível");
+    let mut ultimo_processador = None;
+    // Na fila durável, a entrada parada mais de `fila.ocioso_ms` pode ser reivindicada por
+    // outro consumidor; a posse é conferida antes de cada envio e renovada durante a pausa.
+    let renovacao_posse = Duration::from_millis(state.fila.ocioso_ms / 4);
+    let mut posse_renovada_em = Instant::now();
     payment.update_date();
 
     loop {
+        if state.encerramento.prazo_esgotado() {
+            return transbordar(&state, payment).await;
+        }
+        // Pausa manual: segura o pagamento sem contar tentativa.
+        if intervencao::pausado(&state) {
+            if let Some(posse) = posse
+                && posse_renovada_em.elapsed() >= renovacao_posse
+            {
+                match fila::assumir_entrada(&state, posse).await {
+                    Ok(true) => posse_renovada_em = Instant::now(),
+                    Ok(false) => return entrada_perdida(posse),
+                    Err(e) => warn!(erro = %e, "falha ao renovar a posse da entrada"),
+                }
+            }
+            tokio::time::sleep(max_retry_delay / 4).await;
+            continue;
+        }
+        if let Some(posse) = posse {
+            match fila::assumir_entrada(&state, posse).await {
+                Ok(true) => posse_renovada_em = Instant::now(),
+                Ok(false) => return entrada_perdida(posse),
+                // Sem confirmar a posse não envia; espera sem gastar tentativa.
+                Err(e) => {
+                    warn!(erro = %e, "falha ao conferir a posse da entrada");
+                    tokio::time::sleep(retry_delay).await;
+                    retry_delay = (retry_delay * 2).min(max_retry_delay);
+                    continue;
+                }
+            }
+        }
         if retry_times >= max_retry_times {
-            return;
+            return registra_dead_letter(
+                &state,
+                payment,
+                ultimo_erro,
+                retry_times,
+                ultimo_processador,
+                false,
+                false,
+            )
+            .await;
         }
 
-        let (processor_opt, tipo) =
-            escolher_processador(&state, retry_times, fallback_threshold).await;
-
-        let processor_arc = match processor_opt {
-            Some(p) => p,
+        let (processor_arc, tipo, permit, fallback) = match escolher_processador(
+            &state,
+            valores.roteamento.as_ref(),
+            &ContextoRoteamento {
+                tentativa: retry_times,
+                fallback_threshold,
+                amount: payment.amount.to_reais_f64(),
+            },
+        )
+        .await
+        {
+            Some(escolhido) => escolhido,
             None => {
+                debug!(tentativa = retry_times, "nenhum processador disponível");
                 tokio::time::sleep(retry_delay).await;
                 retry_delay = (retry_delay * 2).min(max_retry_delay);
                 retry_times += 1;
@@ -77,39 +346,269 @@ pub async fn processa_pagamento(state: AppState, mut payment: Payment) {
             }
         };
 
-        let address = {
-            let guard = processor_arc.read().await;
-            guard.address.clone()
-        };
+        let address = processor_arc.read().await.address.clone();
         let payment_url = format!("{}/payments", address);
+        ultimo_processador = Some(tipo.clone());
+        let _ = registrar_tentativa(&state, &payment, &tipo).await;
 
+        let tentativa = info_span!(
+            "tentativa",
+            processador = %tipo,
+            numero = retry_times,
+            status = tracing::field::Empty
+        );
+        let enviado_em = Instant::now();
         let response_result = state
             .http_client
             .post(&payment_url)
+            .timeout(valores.pagamentos.timeout)
             .json(&payment.to_payment_request())
             .send()
+            .instrument(tentativa.clone())
             .await;
+        drop(permit);
 
-        match response_result {
-            Ok(response) if response.status().is_success() => {
-                payment.set_processador(tipo);
-
-                salvar_pagamento(&payment, &state).await;
+        let metricas = &state.metricas;
+        metricas
+            .latencia_processador
+            .with_label_values(&[&tipo])
+            .observe(enviado_em.elapsed().as_secs_f64());
+        let status_http = match &response_result {
+            Ok(response) => response.status().as_u16().to_string(),
+            Err(_) => "erro".to_string(),
+        };
+        metricas
+            .requisicoes_processador
+            .with_label_values(&[&tipo, &status_http])
+            .inc();
+        tentativa.record("status", status_http.as_str());
 
-                return;
+        // 4xx (exceto 429) é problema do pedido, não do processador: não conta contra o disjuntor.
+        let falha_processador = match &response_result {
+            Ok(response) => {
+                response.status().is_server_error()
+                    || response.status() == StatusCode::TOO_MANY_REQUESTS
+            }
+            Err(_) => true,
+        };
+        let timeout = matches!(&response_result, Err(e) if e.is_timeout());
+        {
+            let mut guard = processor_arc.write().await;
+            let agora = Instant::now();
+            if falha_processador {
+                guard.disjuntor.registrar_falha(agora);
+            } else {
+                guard.disjuntor.registrar_sucesso();
             }
+            guard.resultados.local.registrar(
+                agora,
+                enviado_em.elapsed(),
+                falha_processador,
+                timeout,
+            );
+        }
 
-            Ok(_) => {
-                processor_arc.write().await.failing = true;
+        let mut desfecho = match response_result {
+            Ok(response) if response.status().is_success() => Desfecho::Sucesso,
+            Ok(response)
+                if response.status() == StatusCode::CONFLICT
+                    || response.status() == StatusCode::UNPROCESSABLE_ENTITY =>
+            {
+                Desfecho::Ambiguo(format!("processador respondeu {}", response.status()))
             }
+            Ok(response) => Desfecho::Falha(format!("processador respondeu {}", response.status())),
+            // Sem conexão a requisição nunca saiu daqui: reenviar é seguro.
+            Err(e) if e.is_connect() => Desfecho::Falha(format!("falha na requisição: {}", e)),
+            Err(e) => Desfecho::Ambiguo(format!("resultado desconhecido: {}", e)),
+        };
 
-            Err(_) => {
-                processor_arc.write().await.failing = true;
+        if let Desfecho::Ambiguo(erro) = desfecho {
+            tentativa.in_scope(|| debug!(erro = %erro, "resultado ambíguo, consultando"));
+            let _ = registrar_desconhecido(&state, &payment.correlation_id, &erro).await;
+            desfecho = match verificar_cobranca(
+                &state,
+                &address,
+                &payment,
+                enviado_em,
+                &mut retry_times,
+                max_retry_times,
+            )
+            .instrument(tentativa.clone())
+            .await
+            {
+                Verificacao::Cobrado => Desfecho::Sucesso,
+                Verificacao::NaoCobrado => Desfecho::Falha(erro),
+                Verificacao::Esgotado => {
+                    return registra_dead_letter(
+                        &state,
+                        payment,
+                        erro,
+                        retry_times,
+                        Some(tipo),
+                        false,
+                        true,
+                    )
+                    .await;
+                }
+            };
+        }
+
+        match desfecho {
+            Desfecho::Sucesso => {
+                payment.set_processador(tipo.clone());
+
+                let erro = match salvar_pagamento(&state, &payment).await {
+                    Ok(_) => {
+                        metricas.confirmados.with_label_values(&[&tipo]).inc();
+                        if fallback {
+                            metricas.fallback.with_label_values(&[&tipo]).inc();
+                        }
+                        metricas.tentativas.observe(retry_times as f64);
+                        return true;
+                    }
+                    Err(e) => e,
+                };
+                return registra_dead_letter(
+                    &state,
+                    payment,
+                    format!("falha ao gravar pagamento confirmado: {}", erro),
+                    retry_times + 1,
+                    Some(tipo),
+                    true,
+                    false,
+                )
+                .await;
+            }
+            Desfecho::Falha(erro) | Desfecho::Ambiguo(erro) => {
+                tentativa.in_scope(|| debug!(erro = %erro, "tentativa falhou"));
+                ultimo_erro = erro;
             }
         }
+        let _ = registrar_falha(&state, &payment.correlation_id, &ultimo_erro).await;
 
         tokio::time::sleep(retry_delay).await;
         retry_delay = (retry_delay * 2).min(max_retry_delay);
         retry_times += 1;
     }
 }
+
+// Consulta o mesmo processador até ter uma resposta definitiva. Um 404 só é aceito depois
+// da carência: antes disso o POST original ainda pode estar sendo processado lá.
+async fn verificar_cobranca(
+    state: &AppState,
+    address: &str,
+    payment: &Payment,
+    enviado_em: Instant,
+    retry_times: &mut u8,
+    max_retry_times: u8,
+) -> Verificacao {
+    let carencia = Duration::from_millis(state.pagamentos().carencia_consulta_ms);
+    let mut delay = Duration::from_millis(100);
+    let max_delay = Duration::from_millis(constantes::ESPERA_MAX_TENTATIVA_MS);
+
+    // Sem tempo para esperar a carência, o caso vai para a dead letter como desconhecido.
+    while *retry_times < max_retry_times && !state.encerramento.prazo_esgotado() {
+        match consultar_pagamento_processador(&state.http_client, address, &payment.correlation_id)
+            .await
+        {
+            ConsultaPagamento::Cobrado => return Verificacao::Cobrado,
+            ConsultaPagamento::NaoEncontrado if enviado_em.elapsed() >= carencia => {
+                return Verificacao::NaoCobrado;
+            }
+            ConsultaPagamento::NaoEncontrado | ConsultaPagamento::Indefinido(_) => {}
+        }
+
+        *retry_times += 1;
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(max_delay);
+    }
+
+    Verificacao::Esgotado
+}
+
+// Outro consumidor assumiu a entrada e vai enviá-la; esta cópia não é confirmada.
+fn entrada_perdida(posse: fila::Posse<'_>) -> bool {
+    debug!(
+        entrada = posse.id,
+        consumidor = posse.consumidor,
+        "entrada reivindicada por outro consumidor"
+    );
+    false
+}
+
+// Passado o prazo de encerramento, o pagamento volta para o Redis em vez de ser tentado de
+// novo aqui; ele nunca foi cobrado, então outra instância pode recomeçar do zero. Na fila
+// durável ele já está lá: a entrada fica sem confirmação e, depois de `fila.ocioso_ms`, a
+// varredura periódica de um consumidor vivo a reivindica.
+async fn transbordar(state: &AppState, payment: Payment) -> bool {
+    if state.fila.backend == BackendFila::RedisStreams {
+        return false;
+    }
+    match guardar_sobra(state, &payment).await {
+        Ok(()) => {
+            debug!("pagamento guardado para outra instância");
+            true
+        }
+        Err(e) => {
+            registra_dead_letter(
+                state,
+                payment,
+                format!("encerramento sem concluir: {}", e),
+                0,
+                None,
+                false,
+                false,
+            )
+            .await
+        }
+    }
+}
+
+async fn registra_dead_letter(
+    state: &AppState,
+    payment: Payment,
+    ultimo_erro: String,
+    tentativas: u8,
+    processador: Option<String>,
+    cobrado: bool,
+    resultado_desconhecido: bool,
+) -> bool {
+    state.metricas.tentativas.observe(tentativas as f64);
+    let dead_letter = DeadLetter {
+        payment,
+        ultimo_erro,
+        tentativas,
+        processador,
+        cobrado,
+        resultado_desconhecido,
+        registrado_em: Utc::now(),
+    };
+
+    let motivo = match salvar_dead_letter(&dead_letter, state).await {
+        Ok(_) => {
+            warn!(
+                erro = %dead_letter.ultimo_erro,
+                tentativas,
+                cobrado,
+                "pagamento enviado para dead letter"
+            );
+            "dead_letter"
+        }
+        // Nem a dead letter foi gravada: o pagamento se perdeu.
+        Err(e) => {
+            error!(
+                erro = %e,
+                ultimo_erro = %dead_letter.ultimo_erro,
+                cobrado,
+                "pagamento perdido: falha ao gravar dead letter"
+            );
+            "perdido"
+        }
+    };
+    state
+        .metricas
+        .descartados
+        .with_label_values(&[motivo])
+        .inc();
+    motivo == "dead_letter"
+}
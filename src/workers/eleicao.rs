@@ -0,0 +1,43 @@
+use std::time::Instant;
+
+use tracing::{info, warn};
+
+use crate::{api::redis, appstate::AppState};
+
+// Todas as instâncias disputam a concessão a cada `renovacao`; a que ganha coleta a
+// saúde dos processadores até deixar de renovar (queda, Redis inacessível, pausa longa).
+pub async fn cria_worker_eleicao(state: AppState) {
+    let lideranca = state.lideranca.clone();
+
+    loop {
+        let inicio = Instant::now();
+        let era_lider = lideranca.e_lider();
+        match redis::disputar_lideranca(&state).await {
+            Ok(token) => {
+                lideranca.registrar_disputa(token, inicio);
+                if token > 0 && !era_lider {
+                    info!(token, identidade = %lideranca.identidade, "liderança assumida");
+                } else if token == 0 && era_lider {
+                    warn!(identidade = %lideranca.identidade, "liderança perdida");
+                }
+            }
+            // Sem resposta não renova: a liderança local expira junto com a do Redis.
+            Err(e) => warn!(erro = %e, "falha ao disputar a liderança"),
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(lideranca.config.renovacao) => {}
+            _ = state.encerramento.aguardar_inicio() => break,
+        }
+    }
+
+    // Devolve a concessão logo no início do encerramento, para outra instância assumir
+    // sem esperar a expiração.
+    if let Some(token) = lideranca.token_vigente(Instant::now()) {
+        lideranca.renunciar();
+        match redis::liberar_lideranca(&state, token).await {
+            Ok(()) => info!(token, "liderança devolvida no encerramento"),
+            Err(e) => warn!(erro = %e, "falha ao devolver a liderança"),
+        }
+    }
+}
@@ -1,33 +1,58 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use chrono::Utc;
 use reqwest::Version;
 use tokio::sync::RwLock;
+use tracing::{debug, warn};
 
 use crate::{
+    api::redis,
     appstate::AppState,
-    models::processor::{Processor, TipoProcessador},
+    models::processor::{PoliticaSaudeObsoleta, Processor, SaudeProcessador, SnapshotSaude},
 };
 
-pub async fn coleta_saude_processador(state: AppState, tipo: TipoProcessador) {
-    let nats_client = state.nats_client;
-
-    let indice = match tipo {
-        TipoProcessador::Default => 0,
-        TipoProcessador::Fallback => 1,
-        TipoProcessador::None => {
-            return;
-        }
-    };
-
-    let processor_arc = state.processors[indice].clone();
+pub async fn coleta_saude_processador(state: AppState, processor_arc: Arc<RwLock<Processor>>) {
+    let mut degradado = false;
 
-    let address = {
-        let processor_guard = processor_arc.read().await;
-        processor_guard.address.clone() + "/payments/service-health"
-    };
+    let nome = processor_arc.read().await.nome.clone();
 
     loop {
-        let min_response_time = processor_arc.read().await.min_response_time;
+        // Seguidores só acompanham a saúde publicada pela líder, enquanto ela estiver
+        // chegando; depois da validade aplicam `SAUDE_OBSOLETA`.
+        let token = state.lideranca.token_vigente(Instant::now());
+        if token.is_none() {
+            let obsoleta = processor_arc.read().await.saude_obsoleta(
+                Utc::now(),
+                state.iniciado_em,
+                state.saude.validade,
+            );
+            if !obsoleta {
+                degradado = false;
+                tokio::time::sleep(state.lideranca.config.renovacao).await;
+                continue;
+            }
+            if state.saude.politica == PoliticaSaudeObsoleta::Degradar {
+                if !degradado {
+                    warn!(processador = %nome, "saúde obsoleta, seguindo só com o disjuntor");
+                    processor_arc.write().await.failing = false;
+                    degradado = true;
+                }
+                tokio::time::sleep(state.lideranca.config.renovacao).await;
+                continue;
+            }
+            debug!(processador = %nome, "saúde obsoleta, consultando o processador");
+        }
+        // A URL pode mudar numa recarga de configuração.
+        let (address, min_response_time) = {
+            let processor_guard = processor_arc.read().await;
+            (
+                processor_guard.address.clone() + "/payments/service-health",
+                processor_guard.min_response_time,
+            )
+        };
 
         match state
             .http_client
@@ -38,51 +63,90 @@ pub async fn coleta_saude_processador(state: AppState, tipo: TipoProcessador) {
         {
             Ok(_response) => {
                 if _response.status().is_success() {
-                    match _response.json::<Processor>().await {
+                    match _response.json::<SaudeProcessador>().await {
                         Ok(json) => {
-                            let mut processor_guard = processor_arc.write().await;
-                            processor_guard.failing = json.failing;
-                            processor_guard.min_response_time = json.min_response_time;
-                            drop(processor_guard);
-
-                            nats_client
-                                .publish(
-                                    format!("processor.{}.status", indice),
-                                    serde_json::to_string(&json).unwrap().into(),
-                                )
-                                .await
-                                .unwrap();
+                            aplicar_e_publicar(&state, &nome, &processor_arc, json, token).await;
                         }
-                        Err(_) => {
-                            marcar_como_falho(&processor_arc).await;
+                        Err(e) => {
+                            debug!(processador = %nome, erro = %e, "health check com corpo inválido");
+                            marcar_como_falho(&state, &nome, &processor_arc, token).await;
                         }
                     }
                 } else {
-                    marcar_como_falho(&processor_arc).await;
+                    debug!(processador = %nome, status = %_response.status(), "health check recusado");
+                    marcar_como_falho(&state, &nome, &processor_arc, token).await;
                 }
             }
-            Err(_) => {
-                marcar_como_falho(&processor_arc).await;
+            Err(e) => {
+                debug!(processador = %nome, erro = %e, "health check sem resposta");
+                marcar_como_falho(&state, &nome, &processor_arc, token).await;
             }
         };
-        tokio::time::sleep(Duration::from_secs(5) + Duration::from_millis(min_response_time)).await;
+        tokio::time::sleep(state.saude.intervalo + Duration::from_millis(min_response_time)).await;
     }
 }
 
-async fn marcar_como_falho(processor_arc: &Arc<RwLock<Processor>>) {
-    let mut processor_guard = processor_arc.write().await;
-    processor_guard.failing = true;
-    processor_guard.min_response_time = 500;
-    drop(processor_guard);
+// Aplica a medição e, na líder, publica e grava com o token de liderança: a falha segue
+// o mesmo caminho da saúde boa, senão os seguidores continuariam com a anterior.
+async fn aplicar_e_publicar(
+    state: &AppState,
+    nome: &str,
+    processor_arc: &Arc<RwLock<Processor>>,
+    saude: SaudeProcessador,
+    token: Option<u64>,
+) {
+    let agora = Utc::now();
+    processor_arc.write().await.aplicar_saude(&saude, agora);
+
+    // Só a líder publica; o seguidor que consultou por conta própria guarda a medição
+    // para si.
+    let Some(token) = token else {
+        return;
+    };
+    let snapshot = SnapshotSaude {
+        saude,
+        atualizado_em: agora,
+        token,
+    };
+    // Falha ao publicar não derruba a coleta: a próxima rodada tenta de novo.
+    match serde_json::to_string(&snapshot) {
+        Ok(json) => {
+            if let Err(e) = state
+                .nats_client
+                .publish(format!("processor.{}.status", nome), json.into())
+                .await
+            {
+                warn!(processador = %nome, erro = %e, "falha ao publicar a saúde via NATS");
+            }
+        }
+        Err(e) => {
+            warn!(processador = %nome, erro = %e, "falha ao serializar a saúde");
+        }
+    }
+    if let Err(e) = redis::salvar_snapshot_saude(state, nome, &snapshot).await {
+        warn!(processador = %nome, erro = %e, "falha ao gravar a snapshot de saúde");
+    }
+}
+
+async fn marcar_como_falho(
+    state: &AppState,
+    nome: &str,
+    processor_arc: &Arc<RwLock<Processor>>,
+    token: Option<u64>,
+) {
+    state.metricas.saude_erros.with_label_values(&[nome]).inc();
+    let saude = SaudeProcessador {
+        failing: true,
+        min_response_time: 500,
+    };
+    aplicar_e_publicar(state, nome, processor_arc, saude, token).await;
 }
 
 pub async fn cria_worker_coleta_saude(state: AppState) {
-    tokio::spawn(Box::pin(coleta_saude_processador(
-        state.clone(),
-        TipoProcessador::Default,
-    )));
-    tokio::spawn(Box::pin(coleta_saude_processador(
-        state.clone(),
-        TipoProcessador::Fallback,
-    )));
+    for processor_arc in state.processors.iter() {
+        tokio::spawn(Box::pin(coleta_saude_processador(
+            state.clone(),
+            processor_arc.clone(),
+        )));
+    }
 }
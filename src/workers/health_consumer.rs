@@ -1,25 +1,64 @@
 use futures::StreamExt;
+use tracing::{debug, info, warn};
 
-use crate::{appstate::AppState, models::processor::Processor};
+use crate::{api::redis, appstate::AppState, models::processor::SnapshotSaude};
 
 pub async fn cria_worker_confere_saude(state: AppState) {
-    let nats_client = state.nats_client;
+    let nats_client = state.nats_client.clone();
     let mut sub = nats_client.subscribe("processor.*.status").await.unwrap();
 
     while let Some(message) = sub.next().await {
-        if let Some(idx_str) = message
+        let Some(nome) = message
             .subject
             .strip_prefix("processor.")
             .and_then(|s| s.strip_suffix(".status"))
-        {
-            if let Ok(idx) = idx_str.parse::<usize>() {
-                if let Some(processor_lock) = state.processors.get(idx) {
-                    let processor: Processor = serde_json::from_slice(&message.payload).unwrap();
-                    let mut processor_guard = processor_lock.write().await;
-                    processor_guard.failing = processor.failing;
-                    processor_guard.min_response_time = processor.min_response_time;
+        else {
+            continue;
+        };
+        let snapshot = match serde_json::from_slice::<SnapshotSaude>(&message.payload) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                debug!(processador = nome, erro = %e, "saúde inválida recebida via NATS");
+                continue;
+            }
+        };
+        aplicar_snapshot(&state, nome, &snapshot).await;
+    }
+}
+
+// Um processo recém-iniciado parte da última saúde gravada pela líder em vez do padrão
+// otimista (`failing: false`); a idade dela decide se ainda vale.
+pub async fn carregar_saude_persistida(state: &AppState) {
+    for processor_lock in state.processors.iter() {
+        let nome = processor_lock.read().await.nome.clone();
+        match redis::buscar_snapshot_saude(state, &nome).await {
+            Ok(Some(snapshot)) => {
+                if aplicar_snapshot(state, &nome, &snapshot).await {
+                    info!(processador = %nome, atualizado_em = %snapshot.atualizado_em, "saúde persistida carregada");
                 }
             }
+            Ok(None) => {}
+            Err(e) => warn!(processador = %nome, erro = %e, "falha ao carregar a saúde persistida"),
+        }
+    }
+}
+
+async fn aplicar_snapshot(state: &AppState, nome: &str, snapshot: &SnapshotSaude) -> bool {
+    // Fencing: saúde de uma líder já substituída não é aplicada.
+    if !state.lideranca.aceitar_token(snapshot.token) {
+        debug!(
+            processador = nome,
+            token = snapshot.token,
+            "saúde de líder antiga descartada"
+        );
+        return false;
+    }
+
+    for processor_lock in state.processors.iter() {
+        let mut processor_guard = processor_lock.write().await;
+        if processor_guard.nome == nome {
+            return processor_guard.aplicar_saude(&snapshot.saude, snapshot.atualizado_em);
         }
     }
+    false
 }
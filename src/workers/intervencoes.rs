@@ -0,0 +1,40 @@
+use futures::StreamExt;
+use tracing::{debug, info};
+
+use crate::{
+    appstate::AppState,
+    constantes,
+    intervencao::{self, MensagemIntervencao},
+};
+
+// Aplica as intervenções manuais feitas nas outras instâncias.
+pub async fn cria_worker_intervencoes(state: AppState) {
+    let mut sub = state
+        .nats_client
+        .subscribe(constantes::INTERVENCAO_SUBJECT)
+        .await
+        .unwrap();
+
+    while let Some(message) = sub.next().await {
+        let mensagem = match serde_json::from_slice::<MensagemIntervencao>(&message.payload) {
+            Ok(mensagem) => mensagem,
+            Err(e) => {
+                debug!(erro = %e, "intervenção inválida recebida via NATS");
+                continue;
+            }
+        };
+        if mensagem.origem == state.lideranca.identidade {
+            continue;
+        }
+        match intervencao::aplicar(&state, &mensagem).await {
+            Ok(true) => info!(
+                origem = %mensagem.origem,
+                processador = mensagem.processador.as_deref().unwrap_or("*"),
+                intervencao = ?mensagem.intervencao,
+                "intervenção manual recebida"
+            ),
+            Ok(false) => debug!(origem = %mensagem.origem, "intervenção atrasada descartada"),
+            Err(e) => debug!(origem = %mensagem.origem, erro = %e, "intervenção ignorada"),
+        }
+    }
+}
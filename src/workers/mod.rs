@@ -1,3 +1,10 @@
+pub mod carga;
+pub mod compactacao;
 pub mod consumer;
+pub mod eleicao;
 pub mod health_checker;
 pub mod health_consumer;
+pub mod intervencoes;
+pub mod recarga_config;
+pub mod resultados;
+pub mod sobras;
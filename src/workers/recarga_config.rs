@@ -0,0 +1,73 @@
+use futures::StreamExt;
+use tracing::{debug, info, warn};
+
+use crate::{
+    appstate::AppState,
+    constantes,
+    recarga::{self, MensagemRecarga},
+};
+
+// Recebe as recargas repassadas pelas outras instâncias e, em Unix, relê o arquivo de
+// configuração a cada SIGHUP.
+pub async fn cria_worker_recarga(state: AppState) {
+    let mut sub = state
+        .nats_client
+        .subscribe(constantes::RECARGA_SUBJECT)
+        .await
+        .unwrap();
+
+    #[cfg(unix)]
+    let mut sighup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok();
+
+    loop {
+        #[cfg(unix)]
+        let sinal = async {
+            match sighup.as_mut() {
+                Some(sinal) => sinal.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let sinal = std::future::pending::<Option<()>>();
+
+        tokio::select! {
+            message = sub.next() => {
+                let Some(message) = message else { break };
+                aplicar_mensagem(&state, &message.payload).await;
+            }
+            Some(()) = sinal => {
+                info!("SIGHUP: relendo a configuração");
+                let resultado = match state.recarga.reler_arquivo() {
+                    Ok(nova) => recarga::recarregar(&state, nova).await.map(|_| ()),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = resultado {
+                    warn!(erro = %e, "recarga recusada; configuração anterior mantida");
+                }
+            }
+            _ = state.encerramento.aguardar_inicio() => break,
+        }
+    }
+}
+
+async fn aplicar_mensagem(state: &AppState, payload: &[u8]) {
+    let mensagem = match serde_json::from_slice::<MensagemRecarga>(payload) {
+        Ok(mensagem) => mensagem,
+        Err(e) => {
+            debug!(erro = %e, "recarga inválida recebida via NATS");
+            return;
+        }
+    };
+    if mensagem.origem == state.lideranca.identidade {
+        return;
+    }
+
+    // Por cima da configuração desta instância, para não levar o que é dela (porta,
+    // instância, token) junto.
+    let mut nova = state.recarga.config_atual().await;
+    nova.aplicar_recarregavel(&mensagem.config);
+    match recarga::aplicar(state, nova, mensagem.emitida_em).await {
+        Ok(_) => info!(origem = %mensagem.origem, "configuração recarregada pela outra instância"),
+        Err(e) => warn!(origem = %mensagem.origem, erro = %e, "recarga repassada recusada"),
+    }
+}
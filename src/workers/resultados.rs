@@ -0,0 +1,72 @@
+use std::{collections::HashMap, time::Instant};
+
+use futures::StreamExt;
+use tracing::{debug, warn};
+
+use crate::{appstate::AppState, constantes, estatisticas::PublicacaoResultados};
+
+// Cada instância publica a própria janela por processador; nenhuma repassa o que recebeu,
+// então somar a janela local com a última de cada par não conta nada duas vezes.
+pub async fn cria_worker_publica_resultados(state: AppState) {
+    loop {
+        tokio::time::sleep(state.estatisticas.intervalo).await;
+
+        let agora = Instant::now();
+        let mut processadores = HashMap::with_capacity(state.processors.len());
+        for processor in state.processors.iter() {
+            let guard = processor.read().await;
+            processadores.insert(guard.nome.clone(), guard.resultados.local.resumo(agora));
+        }
+        let publicacao = PublicacaoResultados {
+            // HOSTNAME pode se repetir entre réplicas; a identidade da eleição não.
+            instancia: state.lideranca.identidade.clone(),
+            processadores,
+        };
+
+        if let Err(e) = state
+            .nats_client
+            .publish(
+                constantes::ESTATISTICAS_SUBJECT,
+                serde_json::to_vec(&publicacao).unwrap().into(),
+            )
+            .await
+        {
+            warn!(erro = %e, "falha ao publicar as estatísticas dos processadores");
+        }
+    }
+}
+
+pub async fn cria_worker_recebe_resultados(state: AppState) {
+    let mut sub = state
+        .nats_client
+        .subscribe(constantes::ESTATISTICAS_SUBJECT)
+        .await
+        .unwrap();
+    let validade = state.estatisticas.validade_pares();
+
+    while let Some(message) = sub.next().await {
+        let publicacao = match serde_json::from_slice::<PublicacaoResultados>(&message.payload) {
+            Ok(publicacao) => publicacao,
+            Err(e) => {
+                debug!(erro = %e, "estatísticas inválidas recebidas via NATS");
+                continue;
+            }
+        };
+        if publicacao.instancia == state.lideranca.identidade {
+            continue;
+        }
+
+        let agora = Instant::now();
+        for processor in state.processors.iter() {
+            let mut guard = processor.write().await;
+            if let Some(resumo) = publicacao.processadores.get(&guard.nome) {
+                guard.resultados.atualizar_par(
+                    publicacao.instancia.clone(),
+                    resumo.clone(),
+                    agora,
+                    validade,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+use crate::{api::redis, appstate::AppState, models::payment::Payment, workers::consumer};
+
+// Retoma os pagamentos que outra instância (ou esta, antes de reiniciar) guardou ao sair
+// sem concluir. Só faz sentido com a fila em memória; na durável nada sai do stream.
+pub async fn cria_worker_recupera_sobras(state: AppState) {
+    let intervalo = Duration::from_secs(1);
+
+    loop {
+        // Divide as permissões com o caminho direto do handler. A permissão vem antes de
+        // retirar a sobra: no encerramento, o `main` toma todas para esperar as tarefas
+        // diretas, e um pagamento já retirado sem onde rodar se perderia.
+        let permit = tokio::select! {
+            biased;
+            _ = state.encerramento.aguardar_inicio() => return,
+            permit = state.fast_furious.clone().acquire_owned() => match permit {
+                Ok(permit) => permit,
+                Err(_) => return,
+            },
+        };
+        let sobra = match redis::retirar_sobra(&state).await {
+            Ok(Some(sobra)) => sobra,
+            Ok(None) => {
+                drop(permit);
+                tokio::time::sleep(intervalo).await;
+                continue;
+            }
+            Err(e) => {
+                warn!(erro = %e, "falha ao buscar pagamento deixado no encerramento");
+                drop(permit);
+                tokio::time::sleep(intervalo).await;
+                continue;
+            }
+        };
+
+        debug!(
+            correlation_id = %sobra.correlation_id,
+            "retomando pagamento deixado no encerramento"
+        );
+        // `requestedAt` é mantido: pode já ter ido ao processador numa tentativa que falhou.
+        let payment = Payment {
+            tipo: None,
+            ..sobra
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            consumer::processa_pagamento(state, payment, None).await;
+        });
+    }
+}